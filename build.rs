@@ -0,0 +1,182 @@
+// Packs the PNGs in assets/ into a single 2bpp sprite atlas, and generates the Rust module that
+// describes it. Each PNG is a horizontal strip of square frames, so its width must be a multiple
+// of its height. Images may use at most four colours, and each is drawn as the nearest of the
+// four greys in `PALETTE`, which are numbered from lightest to darkest; fully transparent pixels
+// count as the lightest.
+
+use std::env;
+use std::fmt::Write;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+const ASSETS_DIR: &str = "assets";
+const ATLAS_WIDTH: u32 = 64;
+const BLIT_2BPP: u32 = 1;
+// the colour for each 2bpp index, so a sprite's ink keeps its number however few colours it uses
+const PALETTE: [[u8; 3]; 4] = [[235, 238, 236], [206, 208, 207], [116, 116, 116], [0, 0, 0]];
+
+struct Sprite {
+    name: String,
+    frame_size: u32,
+    frame_count: u32,
+    // palette indices, one per pixel, row by row across the whole strip
+    pixels: Vec<u8>,
+}
+
+impl Sprite {
+    fn pixel(&self, frame: u32, x: u32, y: u32) -> u8 {
+        let strip_width = self.frame_size * self.frame_count;
+        self.pixels[(y * strip_width + frame * self.frame_size + x) as usize]
+    }
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", ASSETS_DIR);
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(ASSETS_DIR)
+        .unwrap_or_else(|e| panic!("couldn't read {}: {}", ASSETS_DIR, e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "png"))
+        .collect();
+    paths.sort();
+
+    let sprites: Vec<Sprite> = paths.iter().map(|path| {
+        println!("cargo:rerun-if-changed={}", path.display());
+        load_sprite(path)
+    }).collect();
+
+    let (positions, atlas_height) = pack(&sprites);
+    let atlas = encode_atlas(&sprites, &positions, atlas_height);
+    let module = generate_module(&sprites, &positions, atlas_height, &atlas);
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("atlas.rs"), module).unwrap();
+}
+
+// every fully transparent pixel is the same colour, whatever its RGB
+fn opaque_colour(colour: [u8; 4]) -> Option<[u8; 3]> {
+    if colour[3] == 0 {
+        None
+    } else {
+        Some([colour[0], colour[1], colour[2]])
+    }
+}
+
+fn palette_index(colour: Option<[u8; 3]>) -> u8 {
+    let Some(colour) = colour else {
+        return 0;
+    };
+    let distance = |entry: &[u8; 3]| entry.iter().zip(colour).map(|(a, b)| (*a as i32 - b as i32).pow(2)).sum::<i32>();
+    (0..PALETTE.len()).min_by_key(|index| distance(&PALETTE[*index])).unwrap() as u8
+}
+
+fn load_sprite(path: &Path) -> Sprite {
+    let file = File::open(path).unwrap_or_else(|e| panic!("couldn't open {}: {}", path.display(), e));
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().unwrap_or_else(|e| panic!("couldn't decode {}: {}", path.display(), e));
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).unwrap_or_else(|e| panic!("couldn't decode {}: {}", path.display(), e));
+
+    let colours: Vec<Option<[u8; 3]>> = buffer[..info.buffer_size()]
+        .chunks(info.color_type.samples())
+        .map(|pixel| match info.color_type {
+            png::ColorType::Grayscale => [pixel[0], pixel[0], pixel[0], 255],
+            png::ColorType::GrayscaleAlpha => [pixel[0], pixel[0], pixel[0], pixel[1]],
+            png::ColorType::Rgb => [pixel[0], pixel[1], pixel[2], 255],
+            png::ColorType::Rgba => [pixel[0], pixel[1], pixel[2], pixel[3]],
+            png::ColorType::Indexed => unreachable!("indexed images are expanded by the decoder"),
+        })
+        .map(opaque_colour)
+        .collect();
+
+    let mut distinct: Vec<Option<[u8; 3]>> = colours.clone();
+    distinct.sort();
+    distinct.dedup();
+    if distinct.len() > PALETTE.len() {
+        panic!("{} uses {} colours, but sprites can only have {}", path.display(), distinct.len(), PALETTE.len());
+    }
+    let pixels: Vec<u8> = colours.into_iter().map(palette_index).collect();
+
+    if !info.width.is_multiple_of(info.height) {
+        panic!("{} is {}x{}, but sprites must be a strip of square frames", path.display(), info.width, info.height);
+    }
+    if info.height > ATLAS_WIDTH {
+        panic!("{} is taller than the atlas is wide ({}px)", path.display(), ATLAS_WIDTH);
+    }
+
+    Sprite {
+        name: path.file_stem().unwrap().to_str().unwrap().replace('-', "_"),
+        frame_size: info.height,
+        frame_count: info.width / info.height,
+        pixels,
+    }
+}
+
+// Places frames left to right in rows, starting a new row whenever one doesn't fit.
+// Returns the position of every frame of every sprite, and the height of the atlas.
+fn pack(sprites: &[Sprite]) -> (Vec<Vec<(u32, u32)>>, u32) {
+    let mut x = 0;
+    let mut y = 0;
+    let mut row_height = 0;
+    let positions = sprites.iter().map(|sprite| (0..sprite.frame_count).map(|_| {
+        if x + sprite.frame_size > ATLAS_WIDTH {
+            x = 0;
+            y += row_height;
+            row_height = 0;
+        }
+        let position = (x, y);
+        x += sprite.frame_size;
+        row_height = row_height.max(sprite.frame_size);
+        position
+    }).collect()).collect();
+    (positions, y + row_height)
+}
+
+fn encode_atlas(sprites: &[Sprite], positions: &[Vec<(u32, u32)>], atlas_height: u32) -> Vec<u8> {
+    let mut pixels = vec![0u8; (ATLAS_WIDTH * atlas_height) as usize];
+    for (sprite, frames) in sprites.iter().zip(positions) {
+        for (frame, (frame_x, frame_y)) in frames.iter().enumerate() {
+            for y in 0..sprite.frame_size {
+                for x in 0..sprite.frame_size {
+                    pixels[((frame_y + y) * ATLAS_WIDTH + frame_x + x) as usize] = sprite.pixel(frame as u32, x, y);
+                }
+            }
+        }
+    }
+    // four pixels to a byte, leftmost in the highest bits
+    pixels.chunks(4).map(|chunk| chunk.iter().fold(0, |byte, pixel| (byte << 2) | pixel)).collect()
+}
+
+fn generate_module(sprites: &[Sprite], positions: &[Vec<(u32, u32)>], atlas_height: u32, atlas: &[u8]) -> String {
+    let mut module = String::new();
+    writeln!(module, "// Generated by build.rs from the PNGs in {}/.", ASSETS_DIR).unwrap();
+    writeln!(module).unwrap();
+    writeln!(module, "pub const ATLAS_WIDTH: u32 = {};", ATLAS_WIDTH).unwrap();
+    writeln!(module, "pub const ATLAS_HEIGHT: u32 = {};", atlas_height).unwrap();
+    writeln!(module, "pub const ATLAS_FLAGS: u32 = {}; // BLIT_2BPP", BLIT_2BPP).unwrap();
+    writeln!(module, "pub const ATLAS: [u8; {}] = [", atlas.len()).unwrap();
+    for row in atlas.chunks((ATLAS_WIDTH / 4) as usize) {
+        let bytes: Vec<String> = row.iter().map(|byte| format!("0x{:02x}", byte)).collect();
+        writeln!(module, "    {},", bytes.join(",")).unwrap();
+    }
+    writeln!(module, "];").unwrap();
+
+    for (sprite, frames) in sprites.iter().zip(positions) {
+        let constant = sprite.name.to_uppercase();
+        let frame_list: Vec<String> = frames.iter().map(|(x, y)| format!("({}, {})", x, y)).collect();
+        writeln!(module).unwrap();
+        writeln!(module, "// {}", sprite.name).unwrap();
+        writeln!(module, "pub const {}_WIDTH: u32 = {};", constant, sprite.frame_size).unwrap();
+        writeln!(module, "pub const {}_HEIGHT: u32 = {};", constant, sprite.frame_size).unwrap();
+        writeln!(module, "pub const {}_FLAGS: u32 = {}; // BLIT_2BPP", constant, BLIT_2BPP).unwrap();
+        writeln!(module, "pub const {}_FRAME_COUNT: usize = {};", constant, sprite.frame_count).unwrap();
+        writeln!(module, "const {}_FRAMES: [(u32, u32); {}] = [{}];", constant, sprite.frame_count, frame_list.join(", ")).unwrap();
+        writeln!(module).unwrap();
+        writeln!(module, "pub const fn {}(index: usize) -> Frame {{", sprite.name).unwrap();
+        writeln!(module, "    let (x, y) = {}_FRAMES[index];", constant).unwrap();
+        writeln!(module, "    Frame {{ x, y, width: {0}_WIDTH, height: {0}_HEIGHT, flags: 0 }}", constant).unwrap();
+        writeln!(module, "}}").unwrap();
+    }
+    module
+}
@@ -0,0 +1,149 @@
+use crate::game::{GameState, MAX_PLAYERS};
+use crate::input::{Action, Input};
+use crate::wasm4::{DRAW_COLORS, text, trace};
+
+// all hold fire and focus and press a direction
+const TOGGLE_COMBO: Action = Action::Down;
+const PAUSE_COMBO: Action = Action::Up;
+const STEP_COMBO: Action = Action::Right;
+const SPEED_COMBO: Action = Action::Left;
+const ALL_COMBOS: [Action; 4] = [TOGGLE_COMBO, PAUSE_COMBO, STEP_COMBO, SPEED_COMBO];
+
+// the game advances once every this many frames
+const SLOWDOWNS: [u8; 3] = [1, 2, 4];
+
+fn combo_pressed(direction: Action, input: &Input) -> bool {
+    input.held(Action::Fire) && input.held(Action::Focus) && input.pressed(direction)
+}
+
+/// Keeps the buttons of a debug combo away from the game, from the frame the combo goes off until
+/// fire or focus is let go, so stepping a frame doesn't also fire, focus and move. Only built with
+/// the `debug` feature.
+#[derive(Copy, Clone)]
+pub struct ComboMask {
+    active: bool,
+}
+
+impl ComboMask {
+    pub const fn new() -> Self {
+        Self {
+            active: false,
+        }
+    }
+
+    pub fn update(&mut self, input: &Input) {
+        if ALL_COMBOS.iter().any(|direction| combo_pressed(*direction, input)) {
+            self.active = true;
+        } else if !input.held(Action::Fire) || !input.held(Action::Focus) {
+            self.active = false;
+        }
+    }
+
+    /// The gamepads with the first one cleared while a combo is held. A combo ties up fire, focus
+    /// and a direction, so the rest of the pad isn't worth passing on.
+    pub fn mask(&self, gamepads: [u8; MAX_PLAYERS]) -> [u8; MAX_PLAYERS] {
+        let mut gamepads = gamepads;
+        if self.active {
+            gamepads[0] = 0;
+        }
+        gamepads
+    }
+}
+
+/// Draws hitboxes and the game's internal state over the top of the game. Only built with the
+/// `debug` feature.
+#[derive(Copy, Clone)]
+pub struct DebugOverlay {
+    visible: bool,
+}
+
+impl DebugOverlay {
+    pub const fn new() -> Self {
+        Self {
+            visible: false,
+        }
+    }
+
+    pub fn update(&mut self, input: &Input) {
+        if combo_pressed(TOGGLE_COMBO, input) {
+            self.visible = !self.visible;
+            trace(if self.visible { "debug overlay on" } else { "debug overlay off" });
+        }
+    }
+
+    pub fn render(&self, state: &GameState, camera: (i32, i32)) {
+        if !self.visible {
+            return;
+        }
+        unsafe { *DRAW_COLORS = 0x0040 }
+        for (hitbox, x, y) in state.hitboxes() {
+            hitbox.render(x, y, camera);
+        }
+
+        unsafe { *DRAW_COLORS = 0x0004 }
+        let lines = [
+            format!("Frame: {}", state.time()),
+            format!("RNG: {:08x}", state.random_state()),
+            format!("Sum: {:016x}", state.checksum()),
+            format!("Spawn every: {}", state.entity_spawn_interval()),
+            format!("Free: {}/{}", state.free_slots(), state.entity_slots()),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            text(line.as_str(), 0, 16 + i as i32 * 8);
+        }
+    }
+}
+
+/// Decides which frames the game simulation runs on, so it can be paused and stepped a frame at a
+/// time, or slowed down. Only built with the `debug` feature.
+#[derive(Copy, Clone)]
+pub struct FrameStepper {
+    paused: bool,
+    step: bool,
+    slowdown: u8,
+    frame: u8,
+}
+
+impl FrameStepper {
+    pub const fn new() -> Self {
+        Self {
+            paused: false,
+            step: false,
+            slowdown: 0,
+            frame: 0,
+        }
+    }
+
+    pub fn update(&mut self, input: &Input) {
+        if combo_pressed(PAUSE_COMBO, input) {
+            self.paused = !self.paused;
+        }
+        if combo_pressed(STEP_COMBO, input) {
+            self.paused = true;
+            self.step = true;
+        }
+        if combo_pressed(SPEED_COMBO, input) {
+            self.slowdown = (self.slowdown + 1) % SLOWDOWNS.len() as u8;
+        }
+    }
+
+    /// Whether the game should be updated this frame. Called once a frame.
+    pub fn advance(&mut self) -> bool {
+        if self.paused {
+            let step = self.step;
+            self.step = false;
+            return step;
+        }
+        self.frame = (self.frame + 1) % SLOWDOWNS[self.slowdown as usize];
+        self.frame == 0
+    }
+
+    pub fn render(&self) {
+        unsafe { *DRAW_COLORS = 0x0004 }
+        if self.paused {
+            text("PAUSED", 112, 152);
+        } else if self.slowdown > 0 {
+            text(format!("1/{}", SLOWDOWNS[self.slowdown as usize]).as_str(), 136, 152);
+        }
+    }
+}
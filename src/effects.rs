@@ -0,0 +1,63 @@
+/// Whole-screen feedback: shaking the camera, and freezing the game for a few frames on big hits.
+#[derive(Copy, Clone)]
+pub struct ScreenEffects {
+    shake_frames: u8,
+    hit_stop_frames: u8,
+    hit_stop_cooldown: u8,
+    seed: u32,
+}
+
+// the camera never moves more than this many pixels from the centre
+const MAX_SHAKE: u32 = 3;
+
+// fewest frames between two hit-stops, so a stream of hits doesn't stall the game
+const HIT_STOP_INTERVAL: u8 = 20;
+
+impl ScreenEffects {
+    pub const fn new() -> Self {
+        Self {
+            shake_frames: 0,
+            hit_stop_frames: 0,
+            hit_stop_cooldown: 0,
+            seed: 1,
+        }
+    }
+
+    pub fn shake(&mut self, frames: u8) {
+        self.shake_frames = self.shake_frames.max(frames);
+    }
+
+    pub fn hit_stop(&mut self, frames: u8) {
+        if self.hit_stop_cooldown == 0 {
+            self.hit_stop_frames = frames;
+            self.hit_stop_cooldown = HIT_STOP_INTERVAL;
+        }
+    }
+
+    /// Whether the game update should be skipped this frame.
+    pub fn frozen(&self) -> bool {
+        self.hit_stop_frames > 0
+    }
+
+    /// Offset to draw the game world at. The shake dies down as it runs out.
+    pub fn camera(&self, shake_enabled: bool) -> (i32, i32) {
+        if !shake_enabled || self.shake_frames == 0 {
+            return (0, 0);
+        }
+        let amplitude = (self.shake_frames as u32 / 4).clamp(1, MAX_SHAKE);
+        let x = (self.seed >> 16) % (2 * amplitude + 1);
+        let y = (self.seed >> 24) % (2 * amplitude + 1);
+        (x as i32 - amplitude as i32, y as i32 - amplitude as i32)
+    }
+
+    pub fn update(&mut self) {
+        self.shake_frames = self.shake_frames.saturating_sub(1);
+        self.hit_stop_frames = self.hit_stop_frames.saturating_sub(1);
+        self.hit_stop_cooldown = self.hit_stop_cooldown.saturating_sub(1);
+        self.seed = self.seed.wrapping_mul(1103515245).wrapping_add(12345);
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
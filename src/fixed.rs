@@ -0,0 +1,166 @@
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+const FRACTION_BITS: u32 = 8;
+
+/// A signed 24.8 fixed-point number, for positions and velocities that need to move by less than a
+/// pixel a frame. Everything is integer arithmetic, so the game plays out the same on every machine.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Fixed(i32);
+
+impl Fixed {
+    pub const ZERO: Fixed = Fixed(0);
+    pub const ONE: Fixed = Fixed(1 << FRACTION_BITS);
+
+    pub const fn new(whole: i32) -> Self {
+        Fixed(whole << FRACTION_BITS)
+    }
+
+    /// `numerator / denominator`, rounded towards zero to the nearest 1/256.
+    pub const fn ratio(numerator: i32, denominator: i32) -> Self {
+        Fixed((numerator << FRACTION_BITS) / denominator)
+    }
+
+    /// A value made from a number of 1/256ths, as given by `to_raw`.
+    pub const fn from_raw(raw: i32) -> Self {
+        Fixed(raw)
+    }
+
+    /// The number of 1/256ths, for when only the ratio between two values matters.
+    pub const fn to_raw(self) -> i32 {
+        self.0
+    }
+
+    /// The whole part, rounded down.
+    pub const fn to_int(self) -> i32 {
+        self.0 >> FRACTION_BITS
+    }
+
+    /// The whole part, rounded towards zero.
+    pub const fn trunc(self) -> i32 {
+        self.0 / (1 << FRACTION_BITS)
+    }
+
+    /// The whole part, rounded down and clamped to fit in a `u8`.
+    pub fn to_u8(self) -> u8 {
+        self.to_int().clamp(0, u8::MAX as i32) as u8
+    }
+
+    pub const fn abs(self) -> Self {
+        Fixed(self.0.abs())
+    }
+
+    /// Scales the vector (`x`, `y`) to the given length, keeping its direction. A zero vector stays zero.
+    pub fn scale_to(x: Fixed, y: Fixed, length: Fixed) -> (Fixed, Fixed) {
+        // squared in u64, which has room for the sum even with both at i32::MIN
+        let square = |value: Fixed| value.0.unsigned_abs() as u64 * value.0.unsigned_abs() as u64;
+        let current_length = isqrt(square(x) + square(y)) as i64;
+        if current_length == 0 {
+            return (Fixed::ZERO, Fixed::ZERO);
+        }
+        let scale = |value: Fixed| Fixed((value.0 as i64 * length.0 as i64 / current_length) as i32);
+        (scale(x), scale(y))
+    }
+}
+
+// the largest integer whose square is at most `value`
+fn isqrt(value: u64) -> u64 {
+    let mut remainder = value;
+    let mut root = 0;
+    let mut bit = 1 << 62;
+    while bit > value {
+        bit >>= 2;
+    }
+    while bit != 0 {
+        if remainder >= root + bit {
+            remainder -= root + bit;
+            root = (root >> 1) + bit;
+        } else {
+            root >>= 1;
+        }
+        bit >>= 2;
+    }
+    root
+}
+
+impl Add for Fixed {
+    type Output = Fixed;
+
+    fn add(self, other: Fixed) -> Fixed {
+        Fixed(self.0 + other.0)
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Fixed;
+
+    fn sub(self, other: Fixed) -> Fixed {
+        Fixed(self.0 - other.0)
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Fixed;
+
+    fn mul(self, other: Fixed) -> Fixed {
+        Fixed(((self.0 as i64 * other.0 as i64) >> FRACTION_BITS) as i32)
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Fixed;
+
+    fn neg(self) -> Fixed {
+        Fixed(-self.0)
+    }
+}
+
+impl AddAssign for Fixed {
+    fn add_assign(&mut self, other: Fixed) {
+        self.0 += other.0;
+    }
+}
+
+impl SubAssign for Fixed {
+    fn sub_assign(&mut self, other: Fixed) {
+        self.0 -= other.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn isqrt_finds_exact_roots() {
+        assert_eq!(isqrt(0), 0);
+        assert_eq!(isqrt(1), 1);
+        assert_eq!(isqrt(144), 12);
+        assert_eq!(isqrt(1 << 62), 1 << 31);
+        assert_eq!(isqrt(u32::MAX as u64 * u32::MAX as u64), u32::MAX as u64);
+    }
+
+    #[test]
+    fn isqrt_rounds_down() {
+        assert_eq!(isqrt(2), 1);
+        assert_eq!(isqrt(15), 3);
+        assert_eq!(isqrt(u32::MAX as u64 * u32::MAX as u64 - 1), u32::MAX as u64 - 1);
+        assert_eq!(isqrt(u64::MAX), u32::MAX as u64);
+    }
+
+    #[test]
+    fn scale_to_keeps_the_direction() {
+        assert_eq!(Fixed::scale_to(Fixed::new(3), Fixed::new(-4), Fixed::new(10)), (Fixed::new(6), Fixed::new(-8)));
+        assert_eq!(Fixed::scale_to(Fixed::ZERO, Fixed::ZERO, Fixed::new(10)), (Fixed::ZERO, Fixed::ZERO));
+        // 1/sqrt(2) is 181.02/256, rounded towards zero
+        assert_eq!(Fixed::scale_to(Fixed::ONE, Fixed::ONE, Fixed::ONE), (Fixed::from_raw(181), Fixed::from_raw(181)));
+        assert_eq!(Fixed::scale_to(-Fixed::ONE, -Fixed::ONE, Fixed::ONE), (Fixed::from_raw(-181), Fixed::from_raw(-181)));
+    }
+
+    #[test]
+    fn scale_to_handles_the_largest_values() {
+        let (max, min) = (Fixed::from_raw(i32::MAX), Fixed::from_raw(i32::MIN));
+        assert_eq!(Fixed::scale_to(max, max, Fixed::ONE), (Fixed::from_raw(181), Fixed::from_raw(181)));
+        assert_eq!(Fixed::scale_to(min, min, Fixed::ONE), (Fixed::from_raw(-181), Fixed::from_raw(-181)));
+        assert_eq!(Fixed::scale_to(min, Fixed::ZERO, Fixed::ONE), (-Fixed::ONE, Fixed::ZERO));
+    }
+}
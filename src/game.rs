@@ -1,60 +1,380 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use crate::State;
-use crate::lose::LoseState;
-use crate::sprites::{render_eye, render_powerup, render_ship};
+use crate::effects::ScreenEffects;
+use crate::fixed::Fixed;
+use crate::hitbox::Hitbox;
+use crate::input::{Action, Input};
+use crate::lose::{LoseState, Outcome};
+use crate::mouse::Mouse;
+use crate::particles::Particles;
+use crate::settings::{Firing, Handling, Settings};
+use crate::sfx::{Sfx, SfxQueue};
+use crate::sprites::{AIMER, BULLET, draw, EYE_BLINK, eye_looking, POWERUP_PULSE, ship_banking};
 use crate::State::{Game, Lose};
-use crate::wasm4::{BUTTON_1, BUTTON_DOWN, BUTTON_LEFT, BUTTON_RIGHT, BUTTON_UP, DRAW_COLORS, rect, text, tone, trace};
+use crate::upgrades::{Upgrade, Upgrades};
+use crate::wasm4::{DRAW_COLORS, MOUSE_LEFT, MOUSE_RIGHT, oval, rect, text, trace, vline};
 
-#[derive(Copy, Clone)]
+pub const MAX_PLAYERS: usize = 4;
+
+#[derive(Copy, Clone, Hash)]
 pub struct GameState {
-    player_x: u8,
-    player_y: u8,
-    player_dx: i8,
-    player_dy: i8,
-    player_health: u8,
-    player_hurt_cooldown: u8,
+    players: [Player; MAX_PLAYERS],
+    mode: Mode,
+    lives: Lives,
+    // used instead of each player's own lives when they're shared
+    shared_lives: u8,
     time: u32,
     difficulty: u8,
     entity_spawn_interval: u16,
+    // picked up this run, on top of those already banked from earlier ones
+    coins: u32,
+    // bought in the shop before the run started
+    upgrades: Upgrades,
     entities: [Entity; 64],
 }
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Hash, Debug)]
+struct Player {
+    joined: bool,
+    x: Fixed,
+    y: Fixed,
+    dx: Fixed,
+    dy: Fixed,
+    focused: bool,
+    health: u8,
+    // spare lives, when they aren't shared
+    lives: u8,
+    // also counts down the invulnerability after respawning
+    hurt_cooldown: u8,
+    // frames until the next shot
+    fire_cooldown: u8,
+    // whether the toggle firing setting has the player firing
+    firing_toggled: bool,
+    weapon: u8,
+    bombs: u8,
+    // frames left on each buff, by index
+    buffs: [u16; BUFF_COUNT],
+    // frames survived
+    score: u32,
+    // only counted in versus, where they send garbage to the opponent
+    kills: u32,
+}
+
+const ABSENT_PLAYER: Player = Player {
+    joined: false,
+    x: Fixed::ZERO,
+    y: Fixed::ZERO,
+    dx: Fixed::ZERO,
+    dy: Fixed::ZERO,
+    focused: false,
+    health: 0,
+    lives: 0,
+    hurt_cooldown: 0,
+    fire_cooldown: 0,
+    firing_toggled: false,
+    weapon: 0,
+    bombs: 0,
+    buffs: [0; BUFF_COUNT],
+    score: 0,
+    kills: 0,
+};
+
+const STARTING_HEALTH: u8 = 2;
+const STARTING_WEAPON: u8 = 2;
+const STARTING_LIVES: u8 = 2;
+const HURT_INVULNERABILITY: u8 = 90;
+const RESPAWN_INVULNERABILITY: u8 = 180;
+// enemy bullets this close to where a player respawns are cleared, so they get a moment to react
+const RESPAWN_CLEAR_RADIUS: Fixed = Fixed::new(40);
+const RESPAWN_Y: Fixed = Fixed::new(130);
+/// Taken off each player's score for continuing after a game over.
+pub const CONTINUE_PENALTY: u32 = 1000;
+const STARTING_BOMBS: u8 = 2;
+
+// frames between shots for each weapon level
+fn weapon_cooldown(weapon: u8) -> u8 {
+    match weapon {
+        1 => 30,
+        _ => 10,
+    }
+}
+// a boss shrugs off a bomb, but it still takes a chunk of its health
+const BOMB_BOSS_DAMAGE: u8 = 3;
+const MAX_WEAPON: u8 = 3;
+
+// how each player's ship is drawn, so everyone can tell which one is theirs
+const PLAYER_COLOURS: [u16; MAX_PLAYERS] = [0x2430, 0x4320, 0x3240, 0x4230];
+
+/// Whether players team up against the enemies, or race each other on separate halves of the screen.
+#[derive(Copy, Clone, PartialEq, Hash, Debug)]
+pub enum Mode {
+    Coop,
+    Versus,
+}
+
+impl Mode {
+    pub fn next(self) -> Self {
+        match self {
+            Mode::Coop => Mode::Versus,
+            Mode::Versus => Mode::Coop,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Coop => "Co-op",
+            Mode::Versus => "Versus",
+        }
+    }
+}
+
+/// Whether co-op players draw on one pool of lives, or each have their own and drop out when
+/// theirs run out.
+#[derive(Copy, Clone, PartialEq, Hash, Debug)]
+pub enum Lives {
+    Shared,
+    Split,
+}
+
+impl Lives {
+    pub fn next(self) -> Self {
+        match self {
+            Lives::Shared => Lives::Split,
+            Lives::Split => Lives::Shared,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Lives::Shared => "Shared",
+            Lives::Split => "Split",
+        }
+    }
+}
+
+/// A temporary boost from a pickup, which lasts until its timer runs out.
+#[derive(Copy, Clone, PartialEq, Hash, Debug)]
+enum Buff {
+    // absorbs the next hit, and is used up by it
+    Shield,
+    Speed,
+    RapidFire,
+    // pulls nearby pickups in
+    Magnet,
+}
+
+const BUFF_COUNT: usize = 4;
+const ALL_BUFFS: [Buff; BUFF_COUNT] = [Buff::Shield, Buff::Speed, Buff::RapidFire, Buff::Magnet];
+
+const SPEED_BOOST: Fixed = Fixed::ONE;
+// frames left when a buff starts warning that it's running out
+const BUFF_WARNING: u16 = 120;
+// the buff timers are listed under the rest of the HUD
+const BUFF_TIMERS_Y: i32 = 24;
+// each player's buffs share a row, so four of them fit across the screen after the player number
+const BUFF_BAR_WIDTH: u32 = 24;
+const BUFF_TIMER_SPACING: i32 = 36;
+const MAGNET_RANGE: Fixed = Fixed::new(48);
+const MAGNET_PULL: Fixed = Fixed::new(2);
+// the always-on magnet's range for each level of its upgrade
+const MAGNET_UPGRADE_RANGE: Fixed = Fixed::new(12);
+// a broken shield still gives a moment's grace, without the full blinking
+const SHIELD_BREAK_COOLDOWN: u8 = 30;
+
+impl Buff {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn duration(self) -> u16 {
+        match self {
+            Buff::Shield => 900,
+            Buff::Speed => 480,
+            Buff::RapidFire => 480,
+            Buff::Magnet => 600,
+        }
+    }
+
+    // shown on its pickup and next to its timer bar
+    fn letter(self) -> &'static str {
+        match self {
+            Buff::Shield => "S",
+            Buff::Speed => "F",
+            Buff::RapidFire => "R",
+            Buff::Magnet => "M",
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Hash, Debug)]
 struct Entity {
-    x: u8,
-    y: u8,
+    x: Fixed,
+    y: Fixed,
     size: u8,
-    dx: i8,
-    dy: i8,
+    dx: Fixed,
+    dy: Fixed,
     age: u16,
     entity_type: EntityType,
 }
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Hash, Debug)]
 enum EntityType {
     None,
     Bullet {
-        player: bool
+        // the player who fired it, or none for an enemy's
+        player: Option<u8>,
     },
     BasicEnemy {
         seed: u8,
         aims: bool,
     },
-    PowerUp,
+    // pickups that drift down the screen, like dropped loot, rather than bouncing around it
+    PowerUp {
+        drifting: bool,
+    },
+    BuffPickup {
+        buff: Buff,
+        drifting: bool,
+    },
+    Boss {
+        health: u8,
+    },
+    Coin {
+        value: u8,
+    },
 }
 
+/// What an enemy leaves behind when it's destroyed, as chances out of 100. Both a coin and an item
+/// can drop at once.
+struct DropTable {
+    coin_chance: u32,
+    coin_value: u8,
+    item_chance: u32,
+}
+
+const NO_DROPS: DropTable = DropTable { coin_chance: 0, coin_value: 0, item_chance: 0 };
+
+// harder difficulties pay out more for each coin, but hand out fewer items
+fn drop_table(enemy: EntityType, difficulty: u8) -> DropTable {
+    let bonus = difficulty / 4;
+    let scarcity = difficulty as u32 / 2;
+    match enemy {
+        EntityType::BasicEnemy { aims: false, .. } => DropTable { coin_chance: 30, coin_value: 1 + bonus, item_chance: 10 - scarcity },
+        EntityType::BasicEnemy { aims: true, .. } => DropTable { coin_chance: 50, coin_value: 2 + bonus, item_chance: 15 - scarcity },
+        EntityType::Boss { .. } => DropTable { coin_chance: 100, coin_value: 10 + difficulty, item_chance: 100 },
+        _ => NO_DROPS,
+    }
+}
+
+// how fast drops sink towards the bottom of the screen
+const DROP_DRIFT: Fixed = Fixed::ratio(1, 2);
+// an item dropped alongside a coin starts a little above it, so they don't overlap
+const ITEM_DROP_OFFSET: Fixed = Fixed::new(6);
+
+const BOSS_WAVE_INTERVAL: u32 = 8;
+// the boss music speeds up once a boss has this much health or less left
+const BOSS_LOW_HEALTH: u8 = 5;
+
+// keeps the whole 8x8 ship on screen, or in its own half in versus
+const PLAYER_EDGE_MARGIN: Fixed = Fixed::new(4);
+const SCREEN_SIZE: Fixed = Fixed::new(160);
+const VERSUS_DIVIDER: Fixed = Fixed::new(80);
+// the inclusive left and right edges of each half in versus. The divider itself belongs to the right
+// half, so the left one stops just short of it, and anything pushed up against it stays on its side.
+const LEFT_HALF: (Fixed, Fixed) = (Fixed::ZERO, Fixed::from_raw(VERSUS_DIVIDER.to_raw() - 1));
+const RIGHT_HALF: (Fixed, Fixed) = (VERSUS_DIVIDER, SCREEN_SIZE);
+// every this many enemies a player kills in versus, one is sent over to their opponent
+const KILLS_PER_GARBAGE: u32 = 3;
+// just the middle of the ship, so bullets can graze past its wings
+const PLAYER_HITBOX: Hitbox = Hitbox::rect(Fixed::ONE, Fixed::ONE);
+
+// top speed while focus (BUTTON_2) is held, for squeezing between bullets
+const FOCUS_SPEED: Fixed = Fixed::ratio(3, 4);
+
+struct ShipHandling {
+    // per frame, towards the top speed while a direction is held
+    acceleration: Fixed,
+    // per frame, towards a standstill while no direction is held
+    friction: Fixed,
+    max_speed: Fixed,
+}
+
+fn ship_handling(handling: Handling) -> ShipHandling {
+    match handling {
+        Handling::Tight => ShipHandling {
+            acceleration: Fixed::new(2),
+            friction: Fixed::new(2),
+            max_speed: Fixed::new(2),
+        },
+        Handling::Normal => ShipHandling {
+            acceleration: Fixed::ratio(1, 4),
+            friction: Fixed::ratio(3, 16),
+            max_speed: Fixed::new(2),
+        },
+        Handling::Loose => ShipHandling {
+            acceleration: Fixed::ratio(1, 8),
+            friction: Fixed::ratio(1, 16),
+            max_speed: Fixed::new(2),
+        },
+    }
+}
+
+// moves a velocity towards the target speed for an axis held in `direction` (-1, 0 or 1)
+fn accelerate(velocity: Fixed, direction: i32, max_speed: Fixed, handling: &ShipHandling) -> Fixed {
+    let velocity = velocity.clamp(-max_speed, max_speed);
+    let (target, step) = match direction {
+        0 => (Fixed::ZERO, handling.friction),
+        1.. => (max_speed, handling.acceleration),
+        _ => (-max_speed, handling.acceleration),
+    };
+    if velocity < target {
+        (velocity + step).min(target)
+    } else {
+        (velocity - step).max(target)
+    }
+}
+
+const EYE_BLINK_INTERVAL: u32 = 150;
+
 const EMPTY_ENTITY: Entity = Entity {
-    x: 0,
-    y: 0,
+    x: Fixed::ZERO,
+    y: Fixed::ZERO,
     size: 0,
-    dx: 0,
-    dy: 0,
+    dx: Fixed::ZERO,
+    dy: Fixed::ZERO,
     age: 0,
     entity_type: EntityType::None,
 };
 
 enum GameEvent {
+    PlayerHurt { player: u8 },
+    PowerUp { x: u8, y: u8, player: u8 },
+    BuffCollected { x: u8, y: u8, player: u8, buff: Buff },
+    CoinCollected { x: u8, y: u8, value: u8 },
+    EnemyKilled { x: u8, y: u8, player: u8, enemy: EntityType },
+    EnemyFired { x: u8, y: u8 },
+    BulletImpact { x: u8, y: u8 },
+    BossHit { x: u8, y: u8 },
+    BossKilled { x: u8, y: u8 },
+}
+
+/// Something that happened during a game update which the cart should play or show, but which
+/// doesn't affect the simulation itself.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GameOutput {
+    PlayerShot,
     PlayerHurt,
-    PowerUp
+    PlayerRespawned { x: u8, y: u8 },
+    ShieldBroken { x: u8, y: u8 },
+    Bomb,
+    PowerUp { x: u8, y: u8 },
+    CoinCollected { x: u8, y: u8, value: u8 },
+    EnemyKilled { x: u8, y: u8 },
+    EnemyFired { x: u8, y: u8 },
+    BulletImpact { x: u8, y: u8 },
+    BossHit { x: u8, y: u8 },
+    BossKilled { x: u8, y: u8 },
 }
 
 struct ChangeRequests<'a> {
@@ -63,18 +383,97 @@ struct ChangeRequests<'a> {
     events: Vec<GameEvent>,
 }
 
+impl Player {
+    fn has_buff(&self, buff: Buff) -> bool {
+        self.buffs[buff.index()] > 0
+    }
+
+    fn max_speed(&self, handling: &ShipHandling) -> Fixed {
+        if self.has_buff(Buff::Speed) { handling.max_speed + SPEED_BOOST } else { handling.max_speed }
+    }
+
+    fn update_movement(&mut self, input: &Input, handling: Handling, arena: (Fixed, Fixed)) {
+        let handling = ship_handling(handling);
+        self.focused = input.held(Action::Focus);
+        let max_speed = if self.focused { FOCUS_SPEED } else { self.max_speed(&handling) };
+        let direction_x = input.held(Action::Right) as i32 - input.held(Action::Left) as i32;
+        let direction_y = input.held(Action::Down) as i32 - input.held(Action::Up) as i32;
+        self.dx = accelerate(self.dx, direction_x, max_speed, &handling);
+        self.dy = accelerate(self.dy, direction_y, max_speed, &handling);
+        self.move_within(arena);
+    }
+
+    // heads straight for the pointer at up to the top speed, stopping once it gets there
+    fn follow(&mut self, target_x: Fixed, target_y: Fixed, handling: Handling, arena: (Fixed, Fixed)) {
+        let max_speed = self.max_speed(&ship_handling(handling));
+        self.focused = false;
+        let (dx, dy) = (target_x - self.x, target_y - self.y);
+        (self.dx, self.dy) = if dx * dx + dy * dy > max_speed * max_speed {
+            Fixed::scale_to(dx, dy, max_speed)
+        } else {
+            (dx, dy)
+        };
+        self.move_within(arena);
+    }
+
+    fn move_within(&mut self, (min_x, max_x): (Fixed, Fixed)) {
+        self.x += self.dx;
+        self.y += self.dy;
+        // stop dead against the edges rather than sliding along them at full speed afterwards
+        let (min_x, max_x) = (min_x + PLAYER_EDGE_MARGIN, max_x - PLAYER_EDGE_MARGIN);
+        let (min_y, max_y) = (PLAYER_EDGE_MARGIN, SCREEN_SIZE - PLAYER_EDGE_MARGIN);
+        if self.x < min_x || self.x > max_x {
+            self.x = self.x.clamp(min_x, max_x);
+            self.dx = Fixed::ZERO;
+        }
+        if self.y < min_y || self.y > max_y {
+            self.y = self.y.clamp(min_y, max_y);
+            self.dy = Fixed::ZERO;
+        }
+    }
+}
+
 impl GameState {
-    pub fn new(difficulty: u8) -> Self {
+    /// A new game for the joined players, who all start with the given upgrades. Versus is always
+    /// between players 1 and 2, each with their own health.
+    pub fn new(difficulty: u8, joined: [bool; MAX_PLAYERS], lives: Lives, mode: Mode, upgrades: Upgrades) -> Self {
+        let (joined, lives) = match mode {
+            Mode::Coop => (joined, lives),
+            Mode::Versus => ([true, true, false, false], Lives::Split),
+        };
+        let player_count = joined.iter().filter(|joined| **joined).count() as i32;
+        let mut players = [ABSENT_PLAYER; MAX_PLAYERS];
+        let mut slot = 0;
+        for (player, joined) in players.iter_mut().zip(joined) {
+            if joined {
+                slot += 1;
+                *player = Player {
+                    joined: true,
+                    x: match mode {
+                        // spread out evenly along the bottom of the screen
+                        Mode::Coop => Fixed::new(160 * slot / (player_count + 1)),
+                        // in the middle of their own half
+                        Mode::Versus => Fixed::new(80 * slot - 40),
+                    },
+                    y: Fixed::new(100),
+                    health: STARTING_HEALTH + upgrades.level(Upgrade::Health),
+                    lives: STARTING_LIVES,
+                    weapon: (STARTING_WEAPON + upgrades.level(Upgrade::Weapon)).min(MAX_WEAPON),
+                    bombs: STARTING_BOMBS + upgrades.level(Upgrade::Bombs),
+                    ..ABSENT_PLAYER
+                };
+            }
+        }
         let mut state = Self {
-            player_x: 80,
-            player_y: 100,
-            player_dx: 0,
-            player_dy: 0,
-            player_health: 2,
-            player_hurt_cooldown: 0,
+            players,
+            mode,
+            lives,
+            shared_lives: STARTING_LIVES * player_count as u8,
             time: 0,
             difficulty,
             entity_spawn_interval: (600u16 - 5u16 * (difficulty as u16).saturating_pow(2)).clamp(1, 600),
+            coins: 0,
+            upgrades,
             entities: [EMPTY_ENTITY; 64],
         };
         state
@@ -90,139 +489,363 @@ impl GameState {
         false
     }
 
-    fn update_movement_from_gamepad(&mut self, gamepad: u8) {
-        if gamepad & BUTTON_UP != 0 {
-            self.player_dy -= 1;
+    /// The left and right edges of the part of the screen that the given player plays in.
+    fn player_arena(&self, index: usize) -> (Fixed, Fixed) {
+        match (self.mode, index) {
+            (Mode::Coop, _) => (Fixed::ZERO, SCREEN_SIZE),
+            (Mode::Versus, 0) => LEFT_HALF,
+            (Mode::Versus, _) => RIGHT_HALF,
         }
-        if gamepad & BUTTON_DOWN != 0 {
-            self.player_dy += 1;
+    }
+
+    /// The left and right edges of the part of the screen containing `x`. Entities never leave the
+    /// half they start in during versus.
+    fn arena_at(&self, x: Fixed) -> (Fixed, Fixed) {
+        match self.mode {
+            Mode::Coop => (Fixed::ZERO, SCREEN_SIZE),
+            Mode::Versus if x < VERSUS_DIVIDER => LEFT_HALF,
+            Mode::Versus => RIGHT_HALF,
+        }
+    }
+
+    fn player_lives(&self, index: usize) -> u8 {
+        match self.lives {
+            Lives::Shared => self.shared_lives,
+            Lives::Split => self.players[index].lives,
+        }
+    }
+
+    fn player_alive(&self, index: usize) -> bool {
+        self.players[index].joined && self.players[index].health > 0
+    }
+
+    fn alive_players(&self) -> impl Iterator<Item = (usize, &Player)> {
+        self.players.iter().enumerate().filter(|(index, _)| self.player_alive(*index))
+    }
+
+    /// The position of the living player closest to (`x`, `y`) and in the same part of the screen,
+    /// if there is one.
+    fn nearest_player(&self, x: Fixed, y: Fixed) -> Option<(Fixed, Fixed)> {
+        let arena = self.arena_at(x);
+        self.alive_players()
+            .filter(|(_, player)| self.arena_at(player.x) == arena)
+            .map(|(_, player)| (player.x, player.y))
+            .min_by_key(|(player_x, player_y)| (*player_x - x) * (*player_x - x) + (*player_y - y) * (*player_y - y))
+    }
+
+    // how close pickups have to be for the player's magnet to pull them in, or zero without one
+    fn magnet_range(&self, player: &Player) -> Fixed {
+        let upgrade_range = MAGNET_UPGRADE_RANGE * Fixed::new(self.upgrades.level(Upgrade::Magnet) as i32);
+        if player.has_buff(Buff::Magnet) { MAGNET_RANGE + upgrade_range } else { upgrade_range }
+    }
+
+    /// The position of the closest living player with a magnet in range of (`x`, `y`), if any.
+    fn nearest_magnet(&self, x: Fixed, y: Fixed) -> Option<(Fixed, Fixed)> {
+        let arena = self.arena_at(x);
+        self.alive_players()
+            .filter(|(_, player)| self.arena_at(player.x) == arena)
+            .map(|(_, player)| ((player.x - x) * (player.x - x) + (player.y - y) * (player.y - y), self.magnet_range(player), player.x, player.y))
+            .filter(|(distance, range, _, _)| *distance < *range * *range)
+            .min()
+            .map(|(_, _, player_x, player_y)| (player_x, player_y))
+    }
+
+    fn hurt_player(&mut self, index: usize, outputs: &mut Vec<GameOutput>) {
+        if self.players[index].hurt_cooldown > 0 || !self.player_alive(index) {
+            return;
         }
-        if gamepad & BUTTON_LEFT != 0 {
-            self.player_dx -= 1;
+        let player = &mut self.players[index];
+        if player.has_buff(Buff::Shield) {
+            player.buffs[Buff::Shield.index()] = 0;
+            player.hurt_cooldown = SHIELD_BREAK_COOLDOWN;
+            outputs.push(GameOutput::ShieldBroken { x: player.x.to_u8(), y: player.y.to_u8() });
+            return;
         }
-        if gamepad & BUTTON_RIGHT != 0 {
-            self.player_dx += 1;
+        outputs.push(GameOutput::PlayerHurt);
+        let player = &mut self.players[index];
+        player.health = player.health.saturating_sub(1);
+        player.weapon = player.weapon.saturating_sub(1).max(1);
+        player.hurt_cooldown = HURT_INVULNERABILITY;
+        if player.health == 0 && self.player_lives(index) > 0 {
+            match self.lives {
+                Lives::Shared => self.shared_lives -= 1,
+                Lives::Split => self.players[index].lives -= 1,
+            }
+            self.respawn_player(index, outputs);
         }
     }
 
-    fn update_player(&mut self, gamepad: u8, last_gamepad: u8) {
-        self.player_dx = 0;
-        self.player_dy = 0;
-        self.player_hurt_cooldown = self.player_hurt_cooldown.saturating_sub(1);
+    // brings a player back at the bottom of their part of the screen, with full health
+    fn respawn_player(&mut self, index: usize, outputs: &mut Vec<GameOutput>) {
+        let (min_x, max_x) = self.player_arena(index);
+        let x = (min_x + max_x) * Fixed::ratio(1, 2);
+        self.players[index] = Player {
+            x,
+            y: RESPAWN_Y,
+            dx: Fixed::ZERO,
+            dy: Fixed::ZERO,
+            health: STARTING_HEALTH + self.upgrades.level(Upgrade::Health),
+            hurt_cooldown: RESPAWN_INVULNERABILITY,
+            ..self.players[index]
+        };
+        for entity in self.entities.iter_mut() {
+            let (dx, dy) = (entity.x - x, entity.y - RESPAWN_Y);
+            if entity.entity_type == (EntityType::Bullet { player: None }) && dx * dx + dy * dy < RESPAWN_CLEAR_RADIUS * RESPAWN_CLEAR_RADIUS {
+                outputs.push(GameOutput::BulletImpact { x: entity.x.to_u8(), y: entity.y.to_u8() });
+                *entity = EMPTY_ENTITY;
+            }
+        }
+        outputs.push(GameOutput::PlayerRespawned { x: x.to_u8(), y: RESPAWN_Y.to_u8() });
+    }
 
-        self.update_movement_from_gamepad(gamepad);
-        self.update_movement_from_gamepad(last_gamepad);
+    /// The game carried on after a game over, with everyone back and a fresh set of lives, but
+    /// `CONTINUE_PENALTY` taken off their scores.
+    pub fn continued(self) -> Self {
+        let mut state = self;
+        let mut outputs = Vec::new();
+        let player_count = state.players.iter().filter(|player| player.joined).count() as u8;
+        state.shared_lives = STARTING_LIVES * player_count;
+        for index in 0..MAX_PLAYERS {
+            if state.players[index].joined {
+                let player = &mut state.players[index];
+                player.lives = STARTING_LIVES;
+                player.score = player.score.saturating_sub(CONTINUE_PENALTY);
+                state.respawn_player(index, &mut outputs);
+            }
+        }
+        state
+    }
 
-        self.player_x = if self.player_dx < 0 {
-            self.player_x.saturating_sub(self.player_dx.saturating_abs() as u8)
-        } else {
-            self.player_x.saturating_add(self.player_dx as u8)
-        }.clamp(0, 160);
-        self.player_y = if self.player_dy < 0 {
-            self.player_y.saturating_sub(self.player_dy.saturating_abs() as u8)
-        } else {
-            self.player_y.saturating_add(self.player_dy as u8)
-        }.clamp(0, 160);
-
-        if gamepad & BUTTON_1 != 0 {
-            match self.player_health.min(3) {
-                1 => {
-                    if self.time % 30 == 0 {
-                        shoot_sound();
-                        self.add_entity(Entity {
-                            x: self.player_x,
-                            y: self.player_y.saturating_sub(3),
-                            size: 1,
-                            dx: 0,
-                            dy: -3,
-                            age: 0,
-                            entity_type: EntityType::Bullet { player: true },
-                        });
-                    }
-                }
-                2 => {
-                    if self.time % 10 == 0 {
-                        shoot_sound();
-                        self.add_entity(Entity {
-                            x: self.player_x,
-                            y: self.player_y.saturating_sub(3),
-                            size: 1,
-                            dx: 0,
-                            dy: -3,
-                            age: 0,
-                            entity_type: EntityType::Bullet { player: true },
-                        });
-                    }
-                }
-                3 => {
-                    if self.time % 10 == 0 {
-                        shoot_sound();
-                        self.add_entity(Entity {
-                            x: self.player_x,
-                            y: self.player_y.saturating_sub(3),
-                            size: 1,
-                            dx: 0,
-                            dy: -3,
-                            age: 0,
-                            entity_type: EntityType::Bullet { player: true },
-                        });
-                        self.add_entity(Entity {
-                            x: self.player_x,
-                            y: self.player_y.saturating_sub(3),
-                            size: 1,
-                            dx: -1,
-                            dy: -3,
-                            age: 0,
-                            entity_type: EntityType::Bullet { player: true },
-                        });
-                        self.add_entity(Entity {
-                            x: self.player_x,
-                            y: self.player_y.saturating_sub(3),
-                            size: 1,
-                            dx: 1,
-                            dy: -3,
-                            age: 0,
-                            entity_type: EntityType::Bullet { player: true },
-                        });
-                    }
-                }
-                _ => {}
+    fn give_buff(&mut self, index: usize, buff: Buff) {
+        self.players[index].buffs[buff.index()] = buff.duration();
+    }
+
+    fn power_up_player(&mut self, index: usize) {
+        let player = &mut self.players[index];
+        player.health = player.health.saturating_add(1);
+        player.weapon = (player.weapon + 1).min(MAX_WEAPON);
+    }
+
+    // in versus, every few kills drop an enemy in at the same spot on the opponent's side
+    fn credit_kill(&mut self, index: usize, x: Fixed) {
+        if self.mode != Mode::Versus {
+            return;
+        }
+        let player = &mut self.players[index];
+        player.kills += 1;
+        if player.kills.is_multiple_of(KILLS_PER_GARBAGE) {
+            let x = if x < VERSUS_DIVIDER { x + VERSUS_DIVIDER } else { x - VERSUS_DIVIDER };
+            self.add_entity(Entity {
+                x,
+                y: Fixed::new(10),
+                size: 8,
+                dx: Fixed::ZERO,
+                dy: Fixed::ZERO,
+                age: 0,
+                entity_type: EntityType::BasicEnemy { seed: self.get_random() as u8, aims: true },
+            });
+        }
+    }
+
+    // clears the enemy bullets and basic enemies from the player's part of the screen
+    fn bomb(&mut self, index: usize, outputs: &mut Vec<GameOutput>) {
+        if self.players[index].bombs == 0 {
+            return;
+        }
+        self.players[index].bombs -= 1;
+        outputs.push(GameOutput::Bomb);
+        let arena = self.player_arena(index);
+        let mut kills = Vec::new();
+        for slot in 0..self.entities.len() {
+            let entity = self.entities[slot];
+            if self.arena_at(entity.x) != arena {
+                continue;
+            }
+            let (x, y) = (entity.x.to_u8(), entity.y.to_u8());
+            self.entities[slot] = match entity.entity_type {
+                EntityType::Bullet { player: None } => {
+                    outputs.push(GameOutput::BulletImpact { x, y });
+                    EMPTY_ENTITY
+                },
+                EntityType::BasicEnemy { .. } => {
+                    outputs.push(GameOutput::EnemyKilled { x, y });
+                    kills.push(entity);
+                    EMPTY_ENTITY
+                },
+                EntityType::Boss { health } if health > BOMB_BOSS_DAMAGE => {
+                    outputs.push(GameOutput::BossHit { x, y });
+                    Entity { entity_type: EntityType::Boss { health: health - BOMB_BOSS_DAMAGE }, ..entity }
+                },
+                EntityType::Boss { .. } => {
+                    outputs.push(GameOutput::BossKilled { x, y });
+                    self.drop_loot(entity.entity_type, entity.x, entity.y);
+                    EMPTY_ENTITY
+                },
+                _ => entity,
+            };
+        }
+        for enemy in kills {
+            self.credit_kill(index, enemy.x);
+            self.drop_loot(enemy.entity_type, enemy.x, enemy.y);
+        }
+    }
+
+    // rolls on the enemy's drop table, leaving whatever comes up where it died
+    fn drop_loot(&mut self, enemy: EntityType, x: Fixed, y: Fixed) {
+        let table = drop_table(enemy, self.difficulty);
+        let random = self.get_random() ^ (x.to_int() as u32 * 651) ^ (y.to_int() as u32 * 474);
+        let drop = Entity {
+            x,
+            y,
+            size: 8,
+            dx: Fixed::ZERO,
+            dy: DROP_DRIFT,
+            age: 0,
+            entity_type: EntityType::None,
+        };
+        if random % 100 < table.coin_chance {
+            self.add_entity(Entity { size: 4, entity_type: EntityType::Coin { value: table.coin_value }, ..drop });
+        }
+        if (random >> 8) % 100 < table.item_chance {
+            let item = match (random >> 16) as usize % (BUFF_COUNT + 2) {
+                buff if buff < BUFF_COUNT => EntityType::BuffPickup { buff: ALL_BUFFS[buff], drifting: true },
+                _ => EntityType::PowerUp { drifting: true },
+            };
+            self.add_entity(Entity { y: y - ITEM_DROP_OFFSET, entity_type: item, ..drop });
+        }
+    }
+
+    /// Moves and fires for one player. Player 1 can be given the mouse to steer with instead of
+    /// their gamepad.
+    fn update_player(&mut self, index: usize, input: &Input, mouse: Option<Mouse>, settings: &Settings, outputs: &mut Vec<GameOutput>) {
+        if !self.player_alive(index) {
+            return;
+        }
+        let mut player = self.players[index];
+        player.hurt_cooldown = player.hurt_cooldown.saturating_sub(1);
+        player.fire_cooldown = player.fire_cooldown.saturating_sub(1);
+        for frames in player.buffs.iter_mut() {
+            *frames = frames.saturating_sub(1);
+        }
+        player.score += 1;
+        // on a gamepad, double tapping focus drops a bomb
+        let (fire_held, fire_pressed, bombing) = match mouse {
+            Some(mouse) => {
+                player.follow(Fixed::new(mouse.x), Fixed::new(mouse.y), settings.handling, self.player_arena(index));
+                (mouse.held(MOUSE_LEFT), mouse.pressed(MOUSE_LEFT), mouse.pressed(MOUSE_RIGHT))
+            },
+            None => {
+                player.update_movement(input, settings.handling, self.player_arena(index));
+                (input.held(Action::Fire), input.pressed(Action::Fire), input.double_tapped(Action::Focus))
+            },
+        };
+        if fire_pressed {
+            player.firing_toggled = !player.firing_toggled;
+        }
+        let firing = match settings.firing {
+            Firing::Hold => fire_held,
+            Firing::Toggle => player.firing_toggled,
+            Firing::HoldToStop => !fire_held,
+        };
+        if firing && player.fire_cooldown == 0 {
+            player.fire_cooldown = weapon_cooldown(player.weapon);
+            if player.has_buff(Buff::RapidFire) {
+                player.fire_cooldown /= 2;
             }
+            self.fire(&player, index as u8, outputs);
+        }
+        self.players[index] = player;
+        if bombing {
+            self.bomb(index, outputs);
+        }
+    }
+
+    fn fire(&mut self, player: &Player, index: u8, outputs: &mut Vec<GameOutput>) {
+        outputs.push(GameOutput::PlayerShot);
+        // the top weapon adds a bullet angled out to either side
+        let spread: &[i32] = if player.weapon >= MAX_WEAPON { &[0, -1, 1] } else { &[0] };
+        for dx in spread {
+            self.add_entity(Entity {
+                x: player.x,
+                y: player.y - Fixed::new(3),
+                size: 1,
+                dx: Fixed::new(*dx),
+                dy: Fixed::new(-3),
+                age: 0,
+                entity_type: EntityType::Bullet { player: Some(index) },
+            });
         }
     }
 
     fn spawn_new_entities(&mut self) {
         if self.time % self.entity_spawn_interval as u32 == 0 {
             let mut random = self.get_random();
-            let enemy_count = (random % 6u32) as u8 + (6f32 * self.difficulty as f32 / 10f32) as u8;
+            let enemy_count = (random % 6u32) as u8 + 6 * self.difficulty / 10;
             let x_increment = 160u8/enemy_count;
-            for i in 0..enemy_count {
-                self.add_entity(Entity {
-                    x: i * x_increment,
-                    y: 10,
-                    size: 8,
-                    dx: 0,
-                    dy: 0,
-                    age: 0,
-                    entity_type: EntityType::BasicEnemy { seed: random as u8, aims: self.difficulty > 6 },
-                });
-                random = next_random(random);
+            let wave = self.time / self.entity_spawn_interval as u32;
+            // there's no room for a boss in half the screen, so versus sticks to regular waves
+            if self.mode == Mode::Coop && wave % BOSS_WAVE_INTERVAL == BOSS_WAVE_INTERVAL - 1 {
+                if !self.boss_active() {
+                    self.add_entity(Entity {
+                        x: Fixed::new(80),
+                        y: Fixed::ZERO,
+                        size: 16,
+                        dx: Fixed::ZERO,
+                        dy: Fixed::ZERO,
+                        age: 0,
+                        entity_type: EntityType::Boss { health: 10 + self.difficulty },
+                    });
+                }
+            } else {
+                for i in 0..enemy_count {
+                    let enemy = Entity {
+                        x: Fixed::new((i * x_increment) as i32),
+                        y: Fixed::new(10),
+                        size: 8,
+                        dx: Fixed::ZERO,
+                        dy: Fixed::ZERO,
+                        age: 0,
+                        entity_type: EntityType::BasicEnemy { seed: random as u8, aims: self.difficulty > 6 },
+                    };
+                    self.add_mirrored_entity(enemy);
+                    random = next_random(random);
+                }
             }
             if self.time % 600u32 == 0 {
-                self.add_entity(Entity {
-                    x: (random as u8 % 140u8) + 10u8,
-                    y: ((random >> 8) as u8 % 100u8) + 10u8,
+                // power-ups are as likely as any two buffs together
+                let pickup = match (random >> 4) as usize % (BUFF_COUNT + 2) {
+                    buff if buff < BUFF_COUNT => EntityType::BuffPickup { buff: ALL_BUFFS[buff], drifting: false },
+                    _ => EntityType::PowerUp { drifting: false },
+                };
+                self.add_mirrored_entity(Entity {
+                    x: Fixed::new((random as u8 % 140u8) as i32 + 10),
+                    y: Fixed::new(((random >> 8) as u8 % 100u8) as i32 + 10),
                     size: 8,
-                    dx: ((random >> 16) as u8 % 3u8) as i8 - 1,
-                    dy: ((random >> 24) as u8 % 3u8) as i8 - 1,
+                    dx: Fixed::new(((random >> 16) as u8 % 3u8) as i32 - 1),
+                    dy: Fixed::new(((random >> 24) as u8 % 3u8) as i32 - 1),
                     age: 0,
-                    entity_type: EntityType::PowerUp,
+                    entity_type: pickup,
                 });
             }
         }
     }
 
-    fn with_updated_entities(&self) -> GameState {
+    // in versus, squeezes an entity placed for the whole screen into the left half and puts a copy in
+    // the same spot on the right, so both players face the same thing
+    fn add_mirrored_entity(&mut self, entity: Entity) {
+        match self.mode {
+            Mode::Coop => {
+                self.add_entity(entity);
+            },
+            Mode::Versus => {
+                let left = Entity { x: entity.x * Fixed::ratio(1, 2), ..entity };
+                self.add_entity(left);
+                self.add_entity(Entity { x: left.x + VERSUS_DIVIDER, ..left });
+            },
+        }
+    }
+
+    fn with_updated_entities(&self, outputs: &mut Vec<GameOutput>) -> GameState {
         let self_clone = &self.clone();
         let mut new_state = self.clone();
         let new_entities_and_change_requests: Vec<(Entity, ChangeRequests)> = new_state.entities.iter().map(|entity| entity.update(self_clone)).collect();
@@ -232,7 +855,9 @@ impl GameState {
             new_entities.push(entity);
             change_requests.push(change_request);
         }
-        new_state.entities = <[Entity; 64]>::try_from(new_entities).unwrap();
+        // there's one updated entity per slot, so this can't fail, and not unwrapping keeps the Debug
+        // formatting of every entity out of the cart
+        new_state.entities = <[Entity; 64]>::try_from(new_entities).unwrap_or_else(|_| unreachable!());
 
         for ChangeRequests { entities_to_add, entities_to_remove, events } in change_requests {
             for entity in entities_to_remove {
@@ -248,64 +873,167 @@ impl GameState {
             }
             for event in events {
                 match event {
-                    GameEvent::PlayerHurt => {
-                        if new_state.player_hurt_cooldown == 0 {
-                            hurt_sound();
-                            new_state.player_health = new_state.player_health.saturating_sub(1);
-                            new_state.player_hurt_cooldown = 90;
-                        }
+                    GameEvent::PlayerHurt { player } => new_state.hurt_player(player as usize, outputs),
+                    GameEvent::PowerUp { x, y, player } => {
+                        outputs.push(GameOutput::PowerUp { x, y });
+                        new_state.power_up_player(player as usize);
+                    },
+                    GameEvent::BuffCollected { x, y, player, buff } => {
+                        outputs.push(GameOutput::PowerUp { x, y });
+                        new_state.give_buff(player as usize, buff);
+                    },
+                    GameEvent::CoinCollected { x, y, value } => {
+                        outputs.push(GameOutput::CoinCollected { x, y, value });
+                        new_state.coins += value as u32;
+                    },
+                    GameEvent::EnemyKilled { x, y, player, enemy } => {
+                        outputs.push(GameOutput::EnemyKilled { x, y });
+                        new_state.credit_kill(player as usize, Fixed::new(x as i32));
+                        new_state.drop_loot(enemy, Fixed::new(x as i32), Fixed::new(y as i32));
+                    },
+                    GameEvent::EnemyFired { x, y } => outputs.push(GameOutput::EnemyFired { x, y }),
+                    GameEvent::BulletImpact { x, y } => outputs.push(GameOutput::BulletImpact { x, y }),
+                    GameEvent::BossHit { x, y } => outputs.push(GameOutput::BossHit { x, y }),
+                    GameEvent::BossKilled { x, y } => {
+                        outputs.push(GameOutput::BossKilled { x, y });
+                        new_state.drop_loot(EntityType::Boss { health: 0 }, Fixed::new(x as i32), Fixed::new(y as i32));
                     },
-                    GameEvent::PowerUp => {
-                        power_up_sound();
-                        new_state.player_health = new_state.player_health.saturating_add(1);
-                    }
                 };
             }
         }
         new_state
     }
 
+    /// The average horizontal velocity of the living players, in whole pixels.
+    pub fn player_dx(&self) -> i8 {
+        let (total, count) = self.alive_players().fold((Fixed::ZERO, 0), |(total, count), (_, player)| (total + player.dx, count + 1));
+        if count == 0 { 0 } else { (total.trunc() / count) as i8 }
+    }
+
+    fn any_player_alive(&self) -> bool {
+        self.alive_players().next().is_some()
+    }
+
+    // the game ends once everyone is out, or in versus, once either player is
+    fn over(&self) -> bool {
+        match self.mode {
+            Mode::Coop => !self.any_player_alive(),
+            Mode::Versus => self.alive_players().count() < 2,
+        }
+    }
+
+    fn outcome(&self) -> Outcome {
+        match (self.mode, self.alive_players().next()) {
+            (Mode::Coop, _) => Outcome::GameOver,
+            (Mode::Versus, Some((winner, _))) => Outcome::Winner(winner),
+            (Mode::Versus, None) => Outcome::Draw,
+        }
+    }
+
+    fn scores(&self) -> [Option<u32>; MAX_PLAYERS] {
+        self.players.map(|player| if player.joined { Some(player.score) } else { None })
+    }
+
+    pub fn boss_active(&self) -> bool {
+        self.entities.iter().any(|entity| matches!(entity.entity_type, EntityType::Boss { .. }))
+    }
+
+    /// Whether a boss is down to its last few hits, for the music to pick up.
+    pub fn boss_nearly_beaten(&self) -> bool {
+        self.entities.iter().any(|entity| matches!(entity.entity_type, EntityType::Boss { health } if health <= BOSS_LOW_HEALTH))
+    }
+
     fn get_random(self) -> u32 {
         next_random(self.time)
     }
+
+    /// A hash of the whole simulation. Every cart in a netplay session should get the same one on
+    /// the same frame, so comparing them shows when and where they fell out of sync.
+    pub fn checksum(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
 }
 
+#[cfg(feature = "debug")]
+impl GameState {
+    pub fn time(&self) -> u32 {
+        self.time
+    }
+
+    pub fn entity_spawn_interval(&self) -> u16 {
+        self.entity_spawn_interval
+    }
+
+    pub fn random_state(&self) -> u32 {
+        self.get_random()
+    }
+
+    pub fn free_slots(&self) -> usize {
+        self.entities.iter().filter(|entity| entity.entity_type == EntityType::None).count()
+    }
+
+    pub fn entity_slots(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Every hitbox in play, with the position of its owner, the players' first.
+    pub fn hitboxes(&self) -> Vec<(Hitbox, Fixed, Fixed)> {
+        let mut hitboxes: Vec<(Hitbox, Fixed, Fixed)> = self.alive_players().map(|(_, player)| (PLAYER_HITBOX, player.x, player.y)).collect();
+        for entity in self.entities.iter() {
+            if let Some(hitbox) = entity.entity_type.hitbox() {
+                hitboxes.push((hitbox, entity.x, entity.y));
+            }
+        }
+        hitboxes
+    }
+}
+
+// wraps on overflow, as release builds do, so debug builds and tests don't panic
 fn next_random(seed: u32) -> u32 {
-    (seed * 15417) ^ (seed << 31) ^ ((seed * 123651) >> 7)
+    seed.wrapping_mul(15417) ^ (seed << 31) ^ (seed.wrapping_mul(123651) >> 7)
 }
 
-fn entity_collides_with_wall(entity: &Entity) -> bool {
-    entity.x == 0 && entity.dx < 0 || entity.x == 160 && entity.dx > 0 || entity.y == 0 && entity.dy < 0 || entity.y == 160 && entity.dy > 0
+// whether the entity is moving out through the sides of its arena, or the top or bottom of the screen
+fn entity_collides_with_wall(entity: &Entity, (min_x, max_x): (Fixed, Fixed)) -> bool {
+    entity.x <= min_x && entity.dx < Fixed::ZERO || entity.x >= max_x && entity.dx > Fixed::ZERO
+        || entity.y <= Fixed::ZERO && entity.dy < Fixed::ZERO || entity.y >= SCREEN_SIZE && entity.dy > Fixed::ZERO
 }
 
 fn collides(entity: &Entity, other_entity: &Entity) -> bool {
-    entity.entity_type != EntityType::None && other_entity.entity_type != EntityType::None
-    && entity.x.saturating_sub(entity.size/2) < other_entity.x.saturating_add(other_entity.size/2)
-    && entity.x.saturating_add(entity.size/2) > other_entity.x.saturating_sub(other_entity.size/2)
-    && entity.y.saturating_sub(entity.size/2) < other_entity.y.saturating_add(other_entity.size/2)
-    && entity.y.saturating_add(entity.size/2) > other_entity.y.saturating_sub(other_entity.size/2)
+    match (entity.entity_type.hitbox(), other_entity.entity_type.hitbox()) {
+        (Some(hitbox), Some(other_hitbox)) => hitbox.overlaps(entity.x, entity.y, other_hitbox, other_entity.x, other_entity.y),
+        _ => false,
+    }
 }
 
-fn collides_with_player(entity: &Entity, state: &GameState) -> bool {
-    entity.entity_type != EntityType::None
-        && entity.x.saturating_sub(entity.size/2) < state.player_x.saturating_add(4)
-        && entity.x.saturating_add(entity.size/2) > state.player_x.saturating_sub(4)
-        && entity.y.saturating_sub(entity.size/2) < state.player_y.saturating_add(4)
-        && entity.y.saturating_add(entity.size/2) > state.player_y.saturating_sub(4)
+// the index of the first living player the entity is touching
+fn player_hit_by(entity: &Entity, state: &GameState) -> Option<u8> {
+    let hitbox = entity.entity_type.hitbox()?;
+    state.alive_players()
+        .find(|(_, player)| hitbox.overlaps(entity.x, entity.y, PLAYER_HITBOX, player.x, player.y))
+        .map(|(index, _)| index as u8)
+}
+
+impl EntityType {
+    fn hitbox(&self) -> Option<Hitbox> {
+        match self {
+            EntityType::None => None,
+            EntityType::Bullet { .. } => Some(Hitbox::circle(Fixed::ONE)),
+            EntityType::BasicEnemy { .. } => Some(Hitbox::circle(Fixed::new(3))),
+            // pickups are generous, so grabbing one never needs pixel-perfect flying
+            EntityType::PowerUp { .. } | EntityType::BuffPickup { .. } => Some(Hitbox::rect(Fixed::new(5), Fixed::new(5))),
+            EntityType::Boss { .. } => Some(Hitbox::circle(Fixed::new(7))),
+            EntityType::Coin { .. } => Some(Hitbox::rect(Fixed::new(4), Fixed::new(4))),
+        }
+    }
 }
 
 impl Entity {
-    fn update_movement(&mut self) {
-        self.x = if self.dx < 0 {
-            self.x.saturating_sub(self.dx.saturating_abs() as u8)
-        } else {
-            self.x.saturating_add(self.dx as u8)
-        }.clamp(0, 160);
-        self.y = if self.dy < 0 {
-            self.y.saturating_sub(self.dy.saturating_abs() as u8)
-        } else {
-            self.y.saturating_add(self.dy as u8)
-        }.clamp(0, 160);
+    fn update_movement(&mut self, (min_x, max_x): (Fixed, Fixed)) {
+        self.x = (self.x + self.dx).clamp(min_x, max_x);
+        self.y = (self.y + self.dy).clamp(Fixed::ZERO, SCREEN_SIZE);
     }
 
     fn update(self, state_snapshot: &GameState) -> (Entity, ChangeRequests) {
@@ -316,34 +1044,36 @@ impl Entity {
         };
         let mut new_entity = self.clone();
         new_entity.age += 1;
+        let arena = state_snapshot.arena_at(new_entity.x);
         match new_entity.entity_type {
             EntityType::None => {},
             EntityType::Bullet {player} => {
-                new_entity.update_movement();
-                if new_entity.age > 200 || entity_collides_with_wall(&new_entity) {
+                new_entity.update_movement(arena);
+                if new_entity.age > 200 || entity_collides_with_wall(&new_entity, arena) {
                     new_entity = EMPTY_ENTITY.clone();
                 }
-                if !player && collides_with_player(&new_entity, state_snapshot) {
+                if let (None, Some(hit_player)) = (player, player_hit_by(&new_entity, state_snapshot)) {
+                    change_requests.events.push(GameEvent::BulletImpact { x: new_entity.x.to_u8(), y: new_entity.y.to_u8() });
                     new_entity = EMPTY_ENTITY.clone();
-                    change_requests.events.push(GameEvent::PlayerHurt);
+                    change_requests.events.push(GameEvent::PlayerHurt { player: hit_player });
                 }
             },
             EntityType::BasicEnemy { seed, aims } => {
-                let random = next_random(state_snapshot.get_random() ^ (new_entity.x as u32 * 651) ^ (new_entity.y as u32 * 474));
+                let random = next_random(state_snapshot.get_random() ^ (new_entity.x.to_int() as u32 * 651) ^ (new_entity.y.to_int() as u32 * 474));
                 if (seed as u16 + new_entity.age) % 60 == 0 {
-                    new_entity.dx = if random & 0x10 != 0 { 1 } else { -1 };
-                    new_entity.dy = if random & 0x01 != 0 { 1 } else { -1 };
-                } else if (seed as u16 + new_entity.age) % 60 == 30 || entity_collides_with_wall(&new_entity) {
-                    new_entity.dx = 0;
-                    new_entity.dy = 0;
+                    new_entity.dx = if random & 0x10 != 0 { Fixed::ONE } else { -Fixed::ONE };
+                    new_entity.dy = if random & 0x01 != 0 { Fixed::ONE } else { -Fixed::ONE };
+                } else if (seed as u16 + new_entity.age) % 60 == 30 || entity_collides_with_wall(&new_entity, arena) {
+                    new_entity.dx = Fixed::ZERO;
+                    new_entity.dy = Fixed::ZERO;
                 }
-                new_entity.update_movement();
+                new_entity.update_movement(arena);
                 if (seed as u16 + new_entity.age) % 60 == 0 {
-                    let aim_x = if aims { state_snapshot.player_x as f32 - new_entity.x as f32 } else { 0f32 };
-                    let aim_y = if aims { state_snapshot.player_y as f32 - new_entity.y as f32 } else { 1f32 };
-                    let aim_length = (aim_x as f32 * aim_x as f32 + aim_y as f32 * aim_y as f32).sqrt();
-                    let dx = if aim_length == 0f32 { 0i8 } else { (2f32 * aim_x / aim_length) as i8 };
-                    let dy = if aim_length == 0f32 { 0i8 } else { (2f32 * aim_y / aim_length) as i8 };
+                    let (aim_x, aim_y) = match state_snapshot.nearest_player(new_entity.x, new_entity.y) {
+                        Some((player_x, player_y)) if aims => (player_x - new_entity.x, player_y - new_entity.y),
+                        _ => (Fixed::ZERO, Fixed::ONE),
+                    };
+                    let (dx, dy) = Fixed::scale_to(aim_x, aim_y, Fixed::new(2));
                     change_requests.entities_to_add.push(Entity {
                         x: new_entity.x,
                         y: new_entity.y,
@@ -351,35 +1081,115 @@ impl Entity {
                         dx,
                         dy,
                         age: 0,
-                        entity_type: EntityType::Bullet { player: false },
+                        entity_type: EntityType::Bullet { player: None },
                     });
-                    if collides_with_player(&new_entity, state_snapshot) {
-                        change_requests.events.push(GameEvent::PlayerHurt);
+                    change_requests.events.push(GameEvent::EnemyFired { x: new_entity.x.to_u8(), y: new_entity.y.to_u8() });
+                    if let Some(player) = player_hit_by(&new_entity, state_snapshot) {
+                        change_requests.events.push(GameEvent::PlayerHurt { player });
                     }
                 }
                 for entity in state_snapshot.entities.iter() {
-                    if (entity.entity_type == EntityType::Bullet { player: true }) && collides(&new_entity, entity) {
-                        hit_enemy_sound();
+                    if let (EntityType::Bullet { player: Some(player) }, true) = (entity.entity_type, collides(&new_entity, entity)) {
+                        change_requests.events.push(GameEvent::BulletImpact { x: entity.x.to_u8(), y: entity.y.to_u8() });
+                        change_requests.events.push(GameEvent::EnemyKilled { x: new_entity.x.to_u8(), y: new_entity.y.to_u8(), player, enemy: new_entity.entity_type });
                         new_entity = EMPTY_ENTITY.clone();
                         change_requests.entities_to_remove.push(entity);
                         break;
                     }
                 }
             },
-            EntityType::PowerUp => {
-                if new_entity.x == 0 && new_entity.dx < 0 || new_entity.x == 160 && new_entity.dx > 0 {
-                    new_entity.dx = -new_entity.dx;
+            EntityType::PowerUp { drifting } | EntityType::BuffPickup { drifting, .. } => {
+                let magnet = state_snapshot.nearest_magnet(new_entity.x, new_entity.y);
+                // once a magnet has had hold of a pickup, it drifts off like dropped loot when let go
+                let drifting = drifting || magnet.is_some();
+                if let EntityType::PowerUp { drifting: flag } | EntityType::BuffPickup { drifting: flag, .. } = &mut new_entity.entity_type {
+                    *flag = drifting;
                 }
-                if new_entity.y == 0 && new_entity.dy < 0 || new_entity.y == 160 && new_entity.dy > 0 {
-                    new_entity.dy = -new_entity.dy;
+                match magnet {
+                    Some((player_x, player_y)) => {
+                        (new_entity.dx, new_entity.dy) = Fixed::scale_to(player_x - new_entity.x, player_y - new_entity.y, MAGNET_PULL);
+                    },
+                    None if drifting => (new_entity.dx, new_entity.dy) = (Fixed::ZERO, DROP_DRIFT),
+                    None => {
+                        if new_entity.x <= arena.0 && new_entity.dx < Fixed::ZERO || new_entity.x >= arena.1 && new_entity.dx > Fixed::ZERO {
+                            new_entity.dx = -new_entity.dx;
+                        }
+                        if new_entity.y <= Fixed::ZERO && new_entity.dy < Fixed::ZERO || new_entity.y >= Fixed::new(160) && new_entity.dy > Fixed::ZERO {
+                            new_entity.dy = -new_entity.dy;
+                        }
+                    },
                 }
-                new_entity.update_movement();
-                if new_entity.age > 900 {
+                new_entity.update_movement(arena);
+                // dropped items nobody catches sink out of the bottom of the screen, like coins
+                if new_entity.age > 900 || drifting && entity_collides_with_wall(&new_entity, arena) {
                     new_entity = EMPTY_ENTITY.clone();
                 }
-                if collides_with_player(&new_entity, state_snapshot) {
+                if let Some(player) = player_hit_by(&new_entity, state_snapshot) {
+                    let (x, y) = (new_entity.x.to_u8(), new_entity.y.to_u8());
+                    change_requests.events.push(match new_entity.entity_type {
+                        EntityType::BuffPickup { buff, .. } => GameEvent::BuffCollected { x, y, player, buff },
+                        _ => GameEvent::PowerUp { x, y, player },
+                    });
                     new_entity = EMPTY_ENTITY.clone();
-                    change_requests.events.push(GameEvent::PowerUp);
+                }
+            },
+            EntityType::Coin { value } => {
+                (new_entity.dx, new_entity.dy) = match state_snapshot.nearest_magnet(new_entity.x, new_entity.y) {
+                    Some((player_x, player_y)) => Fixed::scale_to(player_x - new_entity.x, player_y - new_entity.y, MAGNET_PULL),
+                    None => (Fixed::ZERO, DROP_DRIFT),
+                };
+                new_entity.update_movement(arena);
+                // coins nobody catches sink out of the bottom of the screen
+                if new_entity.age > 900 || entity_collides_with_wall(&new_entity, arena) {
+                    new_entity = EMPTY_ENTITY;
+                }
+                if player_hit_by(&new_entity, state_snapshot).is_some() {
+                    change_requests.events.push(GameEvent::CoinCollected { x: new_entity.x.to_u8(), y: new_entity.y.to_u8(), value });
+                    new_entity = EMPTY_ENTITY;
+                }
+            },
+            EntityType::Boss { health } => {
+                // descend onto the screen, then sweep from side to side
+                if new_entity.y < Fixed::new(24) {
+                    new_entity.dy = Fixed::ONE;
+                } else {
+                    new_entity.dy = Fixed::ZERO;
+                    if new_entity.dx == Fixed::ZERO || new_entity.x <= Fixed::new(16) && new_entity.dx < Fixed::ZERO || new_entity.x >= Fixed::new(144) && new_entity.dx > Fixed::ZERO {
+                        new_entity.dx = if new_entity.x < Fixed::new(80) { Fixed::ONE } else { -Fixed::ONE };
+                    }
+                }
+                new_entity.update_movement(arena);
+                if new_entity.age.is_multiple_of(45) {
+                    for dx in -1..=1 {
+                        change_requests.entities_to_add.push(Entity {
+                            x: new_entity.x,
+                            y: new_entity.y + Fixed::new(8),
+                            size: 1,
+                            dx: Fixed::new(dx),
+                            dy: Fixed::new(2),
+                            age: 0,
+                            entity_type: EntityType::Bullet { player: None },
+                        });
+                    }
+                    change_requests.events.push(GameEvent::EnemyFired { x: new_entity.x.to_u8(), y: new_entity.y.to_u8() });
+                }
+                if let Some(player) = player_hit_by(&new_entity, state_snapshot) {
+                    change_requests.events.push(GameEvent::PlayerHurt { player });
+                }
+                let mut remaining_health = health;
+                for entity in state_snapshot.entities.iter() {
+                    if matches!(entity.entity_type, EntityType::Bullet { player: Some(_) }) && collides(&new_entity, entity) {
+                        remaining_health = remaining_health.saturating_sub(1);
+                        change_requests.events.push(GameEvent::BulletImpact { x: entity.x.to_u8(), y: entity.y.to_u8() });
+                        change_requests.entities_to_remove.push(entity);
+                    }
+                }
+                if remaining_health == 0 {
+                    change_requests.events.push(GameEvent::BossKilled { x: new_entity.x.to_u8(), y: new_entity.y.to_u8() });
+                    new_entity = EMPTY_ENTITY;
+                } else if remaining_health < health {
+                    change_requests.events.push(GameEvent::BossHit { x: new_entity.x.to_u8(), y: new_entity.y.to_u8() });
+                    new_entity.entity_type = EntityType::Boss { health: remaining_health };
                 }
             },
         }
@@ -387,63 +1197,568 @@ impl Entity {
     }
 }
 
-pub fn update_game(state: GameState, gamepad: u8, last_gamepad: u8) -> State {
+/// Advances the game by a frame. This has no side effects, so anything the player should hear or
+/// see as a result is returned as a list of outputs.
+pub fn update_game(state: GameState, inputs: &[Input; MAX_PLAYERS], mouse: Option<Mouse>, settings: &Settings) -> (State, Vec<GameOutput>) {
+    let mut outputs = Vec::new();
     let mut new_state = state;
     new_state.spawn_new_entities();
     new_state.time += 1;
-    new_state.update_player(gamepad, last_gamepad);
-    new_state = new_state.with_updated_entities();
+    for (index, input) in inputs.iter().enumerate() {
+        let mouse = if index == 0 { mouse } else { None };
+        new_state.update_player(index, input, mouse, settings, &mut outputs);
+    }
+    new_state = new_state.with_updated_entities(&mut outputs);
 
-    if new_state.player_health == 0 {
-        Lose(LoseState::new(new_state.time))
+    if new_state.over() {
+        // only co-op can be continued; a versus match is settled
+        let continue_from = if new_state.mode == Mode::Coop { Some(new_state) } else { None };
+        (Lose(LoseState::new(new_state.scores(), new_state.outcome(), continue_from)), outputs)
     } else {
-        Game(new_state)
+        (Game(new_state), outputs)
     }
 }
 
-fn render_entities(state: GameState) {
-    state.entities.iter().for_each(|entity| match entity.entity_type {
-        EntityType::None => { },
-        EntityType::Bullet {..} => {
-            unsafe { *DRAW_COLORS = 0x0004 }
-            // draw rect of size entity.size
-            let half_size = (entity.size / 2) as i32;
-            rect((entity.x as i32 - half_size), (entity.y as i32 - half_size), entity.size as u32, entity.size as u32);
-        },
-        EntityType::BasicEnemy {..} => {
-            unsafe { *DRAW_COLORS = 0x0432 }
-            let half_size = (entity.size / 2) as i32;
-            render_eye((entity.x as i32 - half_size), (entity.y as i32 - half_size));
-        },
-        EntityType::PowerUp => {
-            unsafe { *DRAW_COLORS = 0x0432 }
-            let half_size = (entity.size / 2) as i32;
-            render_powerup((entity.x as i32 - half_size), (entity.y as i32 - half_size));
-        },
-    });
+const CHECKSUM_INTERVAL: u32 = 60;
+
+/// Traces the state's checksum every second, for comparing the logs of carts in a netplay session.
+pub fn trace_checksum(state: &GameState) {
+    if state.time.is_multiple_of(CHECKSUM_INTERVAL) {
+        trace(format!("frame {}: checksum {:016x}", state.time, state.checksum()));
+    }
 }
 
-pub fn render_game(state: GameState) {
-    render_entities(state);
-    unsafe { *DRAW_COLORS = 0x2430 }
-    if state.player_hurt_cooldown % 2 == 0 {
-        render_ship((state.player_x as i32 - 4), (state.player_y as i32 - 4));
+/// How many coins were picked up this frame, to be banked in the save.
+pub fn coins_collected(outputs: &[GameOutput]) -> u32 {
+    outputs.iter().map(|output| match *output {
+        GameOutput::CoinCollected { value, .. } => value as u32,
+        _ => 0,
+    }).sum()
+}
+
+pub fn request_game_sounds(outputs: &[GameOutput], sfx: &mut SfxQueue) {
+    for output in outputs {
+        match *output {
+            GameOutput::PlayerShot => sfx.request(Sfx::Shoot),
+            GameOutput::PlayerHurt => sfx.request(Sfx::Hurt),
+            GameOutput::Bomb => sfx.request(Sfx::Bomb),
+            GameOutput::PowerUp { x, .. } | GameOutput::PlayerRespawned { x, .. } => sfx.request_at(Sfx::PowerUp, x),
+            GameOutput::CoinCollected { x, .. } => sfx.request_at(Sfx::Coin, x),
+            GameOutput::ShieldBroken { x, .. } => sfx.request_at(Sfx::HitEnemy, x),
+            GameOutput::EnemyKilled { x, .. } | GameOutput::BossKilled { x, .. } => sfx.request_at(Sfx::HitEnemy, x),
+            GameOutput::EnemyFired { x, .. } => sfx.request_at(Sfx::EnemyShoot, x),
+            GameOutput::BulletImpact { .. } | GameOutput::BossHit { .. } => {},
+        }
     }
-    text(format!("Health: {}", state.player_health).as_str(), 0, 0);
 }
 
-fn shoot_sound() {
-    tone((220 << 16) | 270, 8 << 16, 50, 3);
+pub fn spawn_game_particles(state: &State, outputs: &[GameOutput], particles: &mut Particles) {
+    if let Game(state) = state {
+        for (_, player) in state.alive_players() {
+            particles.thrust(player.x.to_u8(), (player.y + Fixed::new(4)).to_u8());
+        }
+    }
+    for output in outputs {
+        match *output {
+            GameOutput::PowerUp { x, y } | GameOutput::PlayerRespawned { x, y } | GameOutput::CoinCollected { x, y, .. } => particles.sparkle(x, y),
+            GameOutput::EnemyKilled { x, y } => particles.explosion(x, y),
+            GameOutput::BossKilled { x, y } => {
+                particles.explosion(x.saturating_sub(4), y.saturating_sub(4));
+                particles.explosion(x.saturating_add(4), y);
+                particles.explosion(x, y.saturating_add(4));
+            },
+            GameOutput::BulletImpact { x, y } | GameOutput::ShieldBroken { x, y } => particles.sparks(x, y),
+            GameOutput::PlayerShot | GameOutput::PlayerHurt | GameOutput::Bomb | GameOutput::EnemyFired { .. } | GameOutput::BossHit { .. } => {},
+        }
+    }
 }
 
-fn hit_enemy_sound() {
-    tone((70 << 16) | 90, (8 << 16) | (8 << 24), 100, 2);
+pub fn trigger_screen_effects(outputs: &[GameOutput], effects: &mut ScreenEffects) {
+    for output in outputs {
+        match *output {
+            GameOutput::PlayerHurt => effects.shake(12),
+            GameOutput::Bomb => effects.shake(16),
+            GameOutput::BossKilled { .. } => effects.shake(24),
+            GameOutput::BossHit { .. } => effects.hit_stop(3),
+            _ => {},
+        }
+    }
 }
 
-fn hurt_sound() {
-    tone((70 << 16) | 110, (8 << 16) | (8 << 24) | 10, 100, 0);
+fn render_entities(state: GameState, (camera_x, camera_y): (i32, i32)) {
+    state.entities.iter().for_each(|entity| {
+        // enemies keep an eye on whoever is closest, and look down when nobody is left
+        let (target_x, target_y) = state.nearest_player(entity.x, entity.y).unwrap_or((entity.x, Fixed::new(160)));
+        let half_size = (entity.size / 2) as i32;
+        let x = entity.x.to_int() - half_size + camera_x;
+        let y = entity.y.to_int() - half_size + camera_y;
+        match entity.entity_type {
+            EntityType::None => { },
+            EntityType::Bullet {..} => {
+                unsafe { *DRAW_COLORS = 0x4340 }
+                // the sprite is larger than the bullet, and centred on it
+                draw(BULLET.pointing(entity.dx.to_raw(), entity.dy.to_raw()), x - 4, y - 4);
+            },
+            EntityType::BasicEnemy { seed, aims } => {
+                unsafe { *DRAW_COLORS = 0x0432 }
+                // aiming enemies point straight at a player, and the rest blink at their own point
+                // in the cycle and otherwise watch one
+                let blink_time = (entity.age as u32 + seed as u32 * 5) % EYE_BLINK_INTERVAL;
+                let frame = if aims {
+                    AIMER.pointing((target_x - entity.x).to_raw(), (target_y - entity.y).to_raw())
+                } else if blink_time < EYE_BLINK.length() {
+                    EYE_BLINK.frame(blink_time)
+                } else {
+                    eye_looking((target_x - entity.x).to_int(), (target_y - entity.y).to_int())
+                };
+                draw(frame, x, y);
+            },
+            EntityType::PowerUp { .. } => {
+                unsafe { *DRAW_COLORS = 0x0432 }
+                draw(POWERUP_PULSE.frame(entity.age as u32), x, y);
+            },
+            EntityType::BuffPickup { buff, .. } => {
+                unsafe { *DRAW_COLORS = 0x0041 }
+                oval(x - 1, y - 1, 10, 10);
+                unsafe { *DRAW_COLORS = 0x0004 }
+                text(buff.letter(), x, y);
+            },
+            EntityType::Coin { .. } => {
+                unsafe { *DRAW_COLORS = 0x0034 }
+                oval(x, y, entity.size as u32, entity.size as u32);
+            },
+            EntityType::Boss {..} => {
+                unsafe { *DRAW_COLORS = 0x0043 }
+                oval(x, y, entity.size as u32, entity.size as u32);
+                unsafe { *DRAW_COLORS = 0x0432 }
+                let frame = eye_looking((target_x - entity.x).to_int(), (target_y - entity.y).to_int());
+                draw(frame, x + half_size - 4, y + half_size - 4);
+            },
+        }
+    });
+}
+
+/// Draws the game. During netplay, `local_player` is the player this cart belongs to, and every
+/// ship is labelled with its player number.
+pub fn render_game(state: GameState, camera: (i32, i32), local_player: Option<usize>) {
+    if state.mode == Mode::Versus {
+        unsafe { *DRAW_COLORS = 0x0002 }
+        vline(VERSUS_DIVIDER.to_int() + camera.0, camera.1, SCREEN_SIZE.to_int() as u32);
+    }
+    render_entities(state, camera);
+    for (index, player) in state.alive_players() {
+        unsafe { *DRAW_COLORS = PLAYER_COLOURS[index] }
+        if player.hurt_cooldown % 2 == 0 {
+            draw(ship_banking(player.dx.trunc() as i8), player.x.to_int() - 4 + camera.0, player.y.to_int() - 4 + camera.1);
+        }
+        // flickers when it's about to run out
+        let shield = player.buffs[Buff::Shield.index()];
+        if shield > BUFF_WARNING || shield > 0 && shield % 8 < 4 {
+            unsafe { *DRAW_COLORS = 0x0030 }
+            oval(player.x.to_int() - 7 + camera.0, player.y.to_int() - 7 + camera.1, 14, 14);
+        }
+        if player.focused {
+            // the lightest colour stands out against the ship
+            unsafe { *DRAW_COLORS = 0x0011 }
+            PLAYER_HITBOX.render(player.x, player.y, camera);
+        }
+        if let Some(local_player) = local_player {
+            unsafe { *DRAW_COLORS = if index == local_player { 0x0004 } else { 0x0003 } }
+            text(format!("P{}", index + 1).as_str(), player.x.to_int() - 8 + camera.0, player.y.to_int() + 6 + camera.1);
+        }
+    }
+
+    unsafe { *DRAW_COLORS = 0x0004 }
+    let joined: Vec<usize> = (0..MAX_PLAYERS).filter(|index| state.players[*index].joined).collect();
+    if let [index] = joined[..] {
+        let player = state.players[index];
+        text(format!("Health: {}", player.health).as_str(), 0, 0);
+        text(format!("Lives: {}", state.player_lives(index)).as_str(), 0, 8);
+        text(format!("Bombs: {}", player.bombs).as_str(), 0, 16);
+    } else {
+        let per_player = |value: &dyn Fn(usize) -> u8| joined.iter().map(|index| value(*index).to_string()).collect::<Vec<String>>().join(" ");
+        let health: Vec<String> = joined.iter().map(|index| format!("P{}:{}", index + 1, state.players[*index].health)).collect();
+        text(health.join(" ").as_str(), 0, 0);
+        match state.lives {
+            Lives::Shared => text(format!("Lives: {}", state.shared_lives).as_str(), 0, 8),
+            Lives::Split => text(format!("Lives: {}", per_player(&|index| state.players[index].lives)).as_str(), 0, 8),
+        }
+        text(format!("Bombs: {}", per_player(&|index| state.players[index].bombs)).as_str(), 0, 16);
+    }
+    // the run's coins, in the top right below the multiplayer health line
+    let coins = format!("${}", state.coins);
+    text(coins.as_str(), SCREEN_SIZE.to_int() - 8 * coins.len() as i32, 8);
+    render_buff_timers(&state, &joined);
+}
+
+// a row for each player with buffs running, prefixed with their number in multiplayer, and a bar
+// along it for each buff
+fn render_buff_timers(state: &GameState, joined: &[usize]) {
+    let mut y = BUFF_TIMERS_Y;
+    unsafe { *DRAW_COLORS = 0x0004 }
+    for index in joined.iter().copied() {
+        let buffs = state.players[index].buffs;
+        if buffs.iter().all(|frames| *frames == 0) {
+            continue;
+        }
+        let mut x = 0;
+        if joined.len() > 1 {
+            text(format!("{}", index + 1).as_str(), x, y);
+            x += 10;
+        }
+        for buff in ALL_BUFFS {
+            let frames = buffs[buff.index()];
+            if frames == 0 {
+                continue;
+            }
+            text(buff.letter(), x, y);
+            rect(x + 9, y + 2, (frames as u32 * BUFF_BAR_WIDTH / buff.duration() as u32).max(1), 4);
+            x += BUFF_TIMER_SPACING;
+        }
+        y += 8;
+    }
 }
 
-fn power_up_sound() {
-    tone((870 << 16) | 600, (8 << 16) | 12, 100, 2);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Bindings;
+    use crate::wasm4::{BUTTON_1, BUTTON_2, BUTTON_LEFT, BUTTON_RIGHT};
+
+    const PLAYER_BULLET: EntityType = EntityType::Bullet { player: Some(0) };
+    const ENEMY_BULLET: EntityType = EntityType::Bullet { player: None };
+    const BASIC_ENEMY: EntityType = EntityType::BasicEnemy { seed: 0, aims: false };
+    const BOSS: EntityType = EntityType::Boss { health: 10 };
+
+    fn entity(entity_type: EntityType, x: i32, y: i32) -> Entity {
+        Entity { x: Fixed::new(x), y: Fixed::new(y), entity_type, ..EMPTY_ENTITY }
+    }
+
+    fn player_at(x: i32, y: i32) -> GameState {
+        let mut state = GameState::new(0, [true, false, false, false], Lives::Split, Mode::Coop, Upgrades::NONE);
+        state.players[0].x = Fixed::new(x);
+        state.players[0].y = Fixed::new(y);
+        state
+    }
+
+    fn collides_with_player(entity: &Entity, state: &GameState) -> bool {
+        player_hit_by(entity, state).is_some()
+    }
+
+    #[test]
+    fn checksum_changes_with_the_simulation() {
+        let state = player_at(80, 100);
+        assert_eq!(state.checksum(), player_at(80, 100).checksum());
+        let mut moved = state;
+        moved.players[0].x += Fixed::ratio(1, 256);
+        assert_ne!(state.checksum(), moved.checksum());
+    }
+
+    #[test]
+    fn player_bullet_hits_basic_enemy() {
+        assert!(collides(&entity(BASIC_ENEMY, 50, 50), &entity(PLAYER_BULLET, 52, 52)));
+        assert!(!collides(&entity(BASIC_ENEMY, 50, 50), &entity(PLAYER_BULLET, 54, 50)));
+    }
+
+    #[test]
+    fn player_bullet_hits_boss() {
+        assert!(collides(&entity(BOSS, 80, 24), &entity(PLAYER_BULLET, 85, 28)));
+        assert!(!collides(&entity(BOSS, 80, 24), &entity(PLAYER_BULLET, 88, 24)));
+    }
+
+    #[test]
+    fn enemy_bullet_hits_only_the_middle_of_the_player() {
+        let state = player_at(80, 100);
+        assert!(collides_with_player(&entity(ENEMY_BULLET, 80, 100), &state));
+        assert!(collides_with_player(&entity(ENEMY_BULLET, 81, 101), &state));
+        // over the ship's wing, but outside its hitbox
+        assert!(!collides_with_player(&entity(ENEMY_BULLET, 83, 100), &state));
+    }
+
+    #[test]
+    fn basic_enemy_hits_player() {
+        let state = player_at(80, 100);
+        assert!(collides_with_player(&entity(BASIC_ENEMY, 83, 100), &state));
+        assert!(!collides_with_player(&entity(BASIC_ENEMY, 84, 103), &state));
+    }
+
+    #[test]
+    fn boss_hits_player() {
+        let state = player_at(80, 100);
+        assert!(collides_with_player(&entity(BOSS, 80, 93), &state));
+        assert!(!collides_with_player(&entity(BOSS, 80, 92), &state));
+    }
+
+    #[test]
+    fn player_picks_up_power_up_from_further_away() {
+        let state = player_at(80, 100);
+        assert!(collides_with_player(&entity(EntityType::PowerUp { drifting: false }, 85, 105), &state));
+        assert!(!collides_with_player(&entity(EntityType::PowerUp { drifting: false }, 86, 100), &state));
+    }
+
+    #[test]
+    fn empty_slots_never_collide() {
+        let state = player_at(80, 100);
+        assert!(!collides_with_player(&entity(EntityType::None, 80, 100), &state));
+        assert!(!collides(&entity(EntityType::None, 50, 50), &entity(PLAYER_BULLET, 50, 50)));
+        assert!(!collides(&entity(BASIC_ENEMY, 50, 50), &entity(EntityType::None, 50, 50)));
+    }
+
+    #[test]
+    fn enemy_bullet_hits_whichever_player_it_touches() {
+        let mut state = GameState::new(0, [true, true, false, true], Lives::Split, Mode::Coop, Upgrades::NONE);
+        state.players[1].x = Fixed::new(40);
+        state.players[1].y = Fixed::new(60);
+        assert_eq!(player_hit_by(&entity(ENEMY_BULLET, 40, 60), &state), Some(1));
+        // players who have dropped out can't be hit
+        state.players[1].health = 0;
+        assert_eq!(player_hit_by(&entity(ENEMY_BULLET, 40, 60), &state), None);
+    }
+
+    #[test]
+    fn versus_players_stay_in_their_half() {
+        let mut state = GameState::new(0, [true, false, false, false], Lives::Shared, Mode::Versus, Upgrades::NONE);
+        let mut outputs = Vec::new();
+        let (mut right, mut left) = (Input::new(), Input::new());
+        let settings = Settings { handling: Handling::Tight, ..Settings::new() };
+        for _ in 0..120 {
+            right.update(BUTTON_RIGHT, &Bindings::DEFAULT);
+            left.update(BUTTON_LEFT, &Bindings::DEFAULT);
+            state.update_player(0, &right, None, &settings, &mut outputs);
+            state.update_player(1, &left, None, &settings, &mut outputs);
+        }
+        assert_eq!(state.players[0].x, LEFT_HALF.1 - PLAYER_EDGE_MARGIN);
+        assert_eq!(state.players[1].x, VERSUS_DIVIDER + PLAYER_EDGE_MARGIN);
+    }
+
+    #[test]
+    fn versus_enemies_stay_in_their_half() {
+        let state = GameState::new(0, [true, true, false, false], Lives::Split, Mode::Versus, Upgrades::NONE);
+        let mut enemy = Entity { dx: Fixed::ONE, ..entity(BASIC_ENEMY, 78, 50) };
+        for _ in 0..10 {
+            enemy = enemy.update(&state).0;
+            assert_eq!(state.arena_at(enemy.x), LEFT_HALF);
+        }
+        assert_eq!(enemy.x, LEFT_HALF.1);
+    }
+
+    #[test]
+    fn versus_kills_send_garbage_to_the_opponent() {
+        let mut state = GameState::new(0, [true, true, false, false], Lives::Split, Mode::Versus, Upgrades::NONE);
+        let enemies_on_right = |state: &GameState| state.entities.iter()
+            .filter(|entity| matches!(entity.entity_type, EntityType::BasicEnemy { .. }) && entity.x >= VERSUS_DIVIDER)
+            .count();
+        for _ in 1..KILLS_PER_GARBAGE {
+            state.credit_kill(0, Fixed::new(30));
+        }
+        assert_eq!(enemies_on_right(&state), 0);
+        state.credit_kill(0, Fixed::new(30));
+        assert_eq!(enemies_on_right(&state), 1);
+        assert!(state.entities.iter().any(|entity| entity.x == Fixed::new(110)));
+    }
+
+    #[test]
+    fn versus_ends_when_either_player_is_out() {
+        let mut state = GameState::new(0, [true, true, false, false], Lives::Split, Mode::Versus, Upgrades::NONE);
+        assert!(!state.over());
+        state.players[0].health = 0;
+        assert!(state.over());
+        assert_eq!(state.outcome(), Outcome::Winner(1));
+    }
+
+    #[test]
+    fn bomb_clears_enemies_and_their_bullets() {
+        let mut state = player_at(80, 100);
+        state.add_entity(entity(ENEMY_BULLET, 20, 20));
+        state.add_entity(entity(BASIC_ENEMY, 40, 20));
+        state.add_entity(entity(PLAYER_BULLET, 80, 80));
+        let mut outputs = Vec::new();
+        state.bomb(0, &mut outputs);
+        assert_eq!(state.players[0].bombs, STARTING_BOMBS - 1);
+        let remaining: Vec<EntityType> = state.entities.iter().map(|entity| entity.entity_type).filter(|entity_type| *entity_type != EntityType::None).collect();
+        assert_eq!(remaining, vec![PLAYER_BULLET]);
+        assert!(outputs.contains(&GameOutput::Bomb));
+    }
+
+    #[test]
+    fn double_tapping_focus_drops_a_bomb() {
+        let mut state = GameState::new(0, [true, true, false, false], Lives::Split, Mode::Coop, Upgrades::NONE);
+        state.add_entity(entity(ENEMY_BULLET, 20, 20));
+        let (mut input, settings, mut outputs) = (Input::new(), Settings::new(), Vec::new());
+        for gamepad in [BUTTON_2, 0, BUTTON_2] {
+            input.update(gamepad, &Bindings::DEFAULT);
+            state.update_player(1, &input, None, &settings, &mut outputs);
+        }
+        assert_eq!(state.players[1].bombs, STARTING_BOMBS - 1);
+        assert!(state.entities.iter().all(|entity| entity.entity_type == EntityType::None));
+    }
+
+    fn player_bullets(state: &GameState) -> usize {
+        state.entities.iter().filter(|entity| entity.entity_type == PLAYER_BULLET).count()
+    }
+
+    #[test]
+    fn first_shot_fires_straight_away_then_waits_for_the_cooldown() {
+        let mut state = player_at(80, 100);
+        state.time = 7;
+        state.players[0].weapon = 1;
+        let (mut input, settings, mut outputs) = (Input::new(), Settings::new(), Vec::new());
+        input.update(BUTTON_1, &Bindings::DEFAULT);
+        state.update_player(0, &input, None, &settings, &mut outputs);
+        assert_eq!(player_bullets(&state), 1);
+        for _ in 1..weapon_cooldown(1) {
+            input.update(BUTTON_1, &Bindings::DEFAULT);
+            state.update_player(0, &input, None, &settings, &mut outputs);
+        }
+        assert_eq!(player_bullets(&state), 1);
+        input.update(BUTTON_1, &Bindings::DEFAULT);
+        state.update_player(0, &input, None, &settings, &mut outputs);
+        assert_eq!(player_bullets(&state), 2);
+    }
+
+    #[test]
+    fn firing_settings_change_what_the_button_does() {
+        let (mut idle, mut tap, mut outputs) = (Input::new(), Input::new(), Vec::new());
+        idle.update(0, &Bindings::DEFAULT);
+        tap.update(BUTTON_1, &Bindings::DEFAULT);
+
+        let mut state = player_at(80, 100);
+        let settings = Settings { firing: Firing::HoldToStop, ..Settings::new() };
+        state.update_player(0, &idle, None, &settings, &mut outputs);
+        assert_eq!(player_bullets(&state), 1);
+
+        let mut state = player_at(80, 100);
+        let settings = Settings { firing: Firing::Toggle, ..Settings::new() };
+        state.update_player(0, &tap, None, &settings, &mut outputs);
+        state.players[0].fire_cooldown = 0;
+        state.update_player(0, &idle, None, &settings, &mut outputs);
+        assert_eq!(player_bullets(&state), 2);
+    }
+
+    #[test]
+    fn losing_all_health_spends_a_life_and_respawns() {
+        let mut state = player_at(20, 20);
+        state.players[0].health = 1;
+        state.add_entity(entity(ENEMY_BULLET, 80, 120));
+        state.add_entity(entity(ENEMY_BULLET, 20, 30));
+        let mut outputs = Vec::new();
+        state.hurt_player(0, &mut outputs);
+        let player = state.players[0];
+        assert_eq!((player.health, player.lives), (STARTING_HEALTH, STARTING_LIVES - 1));
+        assert_eq!((player.x, player.y), (Fixed::new(80), RESPAWN_Y));
+        assert_eq!(player.hurt_cooldown, RESPAWN_INVULNERABILITY);
+        // only the bullet near the respawn point is cleared
+        assert_eq!(state.entities.iter().filter(|entity| entity.entity_type == ENEMY_BULLET).count(), 1);
+    }
+
+    #[test]
+    fn out_of_lives_means_out_until_continuing() {
+        let mut state = player_at(80, 100);
+        state.players[0].score = 1500;
+        state.players[0].lives = 0;
+        state.players[0].health = 1;
+        state.hurt_player(0, &mut Vec::new());
+        assert!(state.over());
+        let state = state.continued();
+        assert!(!state.over());
+        assert_eq!(state.players[0].lives, STARTING_LIVES);
+        assert_eq!(state.players[0].score, 1500 - CONTINUE_PENALTY);
+    }
+
+    #[test]
+    fn shield_absorbs_one_hit() {
+        let mut state = player_at(80, 100);
+        state.give_buff(0, Buff::Shield);
+        let mut outputs = Vec::new();
+        state.hurt_player(0, &mut outputs);
+        assert_eq!(state.players[0].health, STARTING_HEALTH);
+        assert!(!state.players[0].has_buff(Buff::Shield));
+        state.players[0].hurt_cooldown = 0;
+        state.hurt_player(0, &mut outputs);
+        assert_eq!(state.players[0].health, STARTING_HEALTH - 1);
+    }
+
+    #[test]
+    fn magnet_pulls_in_nearby_pickups() {
+        let mut state = player_at(80, 100);
+        let pickup = entity(EntityType::BuffPickup { buff: Buff::Speed, drifting: false }, 80, 60);
+        assert_eq!(pickup.update(&state).0.dy, Fixed::ZERO);
+        state.give_buff(0, Buff::Magnet);
+        assert_eq!(pickup.update(&state).0.dy, MAGNET_PULL);
+        let far_pickup = entity(EntityType::BuffPickup { buff: Buff::Speed, drifting: false }, 80, 20);
+        assert_eq!(far_pickup.update(&state).0.dy, Fixed::ZERO);
+        // let go of, a pickup drifts down at its usual speed rather than keeping the magnet's
+        let pulled = Entity { dy: MAGNET_PULL, ..entity(EntityType::BuffPickup { buff: Buff::Speed, drifting: true }, 80, 20) };
+        assert_eq!(pulled.update(&state).0.dy, DROP_DRIFT);
+        assert_eq!(pickup.update(&state).0.entity_type, EntityType::BuffPickup { buff: Buff::Speed, drifting: true });
+    }
+
+    #[test]
+    fn tougher_enemies_and_difficulties_drop_more() {
+        let aimer = EntityType::BasicEnemy { seed: 0, aims: true };
+        assert!(drop_table(aimer, 7).coin_chance > drop_table(BASIC_ENEMY, 7).coin_chance);
+        assert!(drop_table(BASIC_ENEMY, 10).coin_value > drop_table(BASIC_ENEMY, 3).coin_value);
+        assert_eq!(drop_table(PLAYER_BULLET, 7).coin_chance, 0);
+        // a boss always leaves a coin and an item behind
+        let mut state = player_at(80, 140);
+        state.drop_loot(BOSS, Fixed::new(80), Fixed::new(24));
+        let drops: Vec<EntityType> = state.entities.iter().map(|entity| entity.entity_type).filter(|entity_type| *entity_type != EntityType::None).collect();
+        assert_eq!(drops.len(), 2);
+        assert!(drops.contains(&EntityType::Coin { value: 10 }));
+    }
+
+    #[test]
+    fn coins_drift_down_and_are_collected() {
+        let mut state = player_at(80, 100);
+        state.add_entity(Entity { dy: DROP_DRIFT, ..entity(EntityType::Coin { value: 3 }, 80, 98) });
+        let mut outputs = Vec::new();
+        for _ in 0..4 {
+            state = state.with_updated_entities(&mut outputs);
+        }
+        assert_eq!(state.coins, 3);
+        assert_eq!(coins_collected(&outputs), 3);
+        // out of reach, a coin sinks off the bottom of the screen
+        let coin = entity(EntityType::Coin { value: 1 }, 20, 160);
+        assert_eq!(coin.update(&state).0.entity_type, EntityType::None);
+    }
+
+    #[test]
+    fn dropped_items_sink_off_the_screen() {
+        let state = player_at(80, 100);
+        // items that appear on their own bounce off the bottom, but dropped ones leave
+        let spawned = Entity { dy: DROP_DRIFT, ..entity(EntityType::PowerUp { drifting: false }, 20, 160) };
+        assert_eq!(spawned.update(&state).0.dy, -DROP_DRIFT);
+        let dropped = Entity { dy: DROP_DRIFT, ..entity(EntityType::PowerUp { drifting: true }, 20, 160) };
+        assert_eq!(dropped.update(&state).0.entity_type, EntityType::None);
+    }
+
+    #[test]
+    fn upgrades_apply_to_new_runs() {
+        let mut upgrades = Upgrades::NONE;
+        let mut coins = 1000;
+        upgrades.buy(Upgrade::Health, &mut coins);
+        upgrades.buy(Upgrade::Weapon, &mut coins);
+        upgrades.buy(Upgrade::Bombs, &mut coins);
+        let state = GameState::new(0, [true, true, false, false], Lives::Split, Mode::Coop, upgrades);
+        let player = state.players[1];
+        assert_eq!((player.health, player.weapon, player.bombs), (STARTING_HEALTH + 1, MAX_WEAPON, STARTING_BOMBS + 1));
+        // the magnet upgrade pulls in pickups without the buff
+        let pickup = entity(EntityType::PowerUp { drifting: false }, state.players[0].x.to_int(), 90);
+        assert_eq!(pickup.update(&state).0.dy, Fixed::ZERO);
+        upgrades.buy(Upgrade::Magnet, &mut coins);
+        let state = GameState::new(0, [true, true, false, false], Lives::Split, Mode::Coop, upgrades);
+        assert_eq!(pickup.update(&state).0.dy, MAGNET_PULL);
+    }
+
+    #[test]
+    fn buffs_run_out() {
+        let mut state = player_at(80, 100);
+        state.give_buff(0, Buff::RapidFire);
+        let (input, settings) = (Input::new(), Settings::new());
+        for _ in 0..Buff::RapidFire.duration() {
+            assert!(state.players[0].has_buff(Buff::RapidFire));
+            state.update_player(0, &input, None, &settings, &mut Vec::new());
+        }
+        assert!(!state.players[0].has_buff(Buff::RapidFire));
+    }
 }
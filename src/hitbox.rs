@@ -0,0 +1,143 @@
+use crate::fixed::Fixed;
+use crate::wasm4::{oval, rect};
+
+/// The area of an entity that can be hit, relative to its position. This is independent of the
+/// sprite, which is usually drawn a little larger.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Hitbox {
+    Rect {
+        offset_x: Fixed,
+        offset_y: Fixed,
+        half_width: Fixed,
+        half_height: Fixed,
+    },
+    Circle {
+        offset_x: Fixed,
+        offset_y: Fixed,
+        radius: Fixed,
+    },
+}
+
+impl Hitbox {
+    pub const fn rect(half_width: Fixed, half_height: Fixed) -> Self {
+        Hitbox::Rect { offset_x: Fixed::ZERO, offset_y: Fixed::ZERO, half_width, half_height }
+    }
+
+    pub const fn circle(radius: Fixed) -> Self {
+        Hitbox::Circle { offset_x: Fixed::ZERO, offset_y: Fixed::ZERO, radius }
+    }
+
+    fn centre(self, x: Fixed, y: Fixed) -> (Fixed, Fixed) {
+        match self {
+            Hitbox::Rect { offset_x, offset_y, .. } | Hitbox::Circle { offset_x, offset_y, .. } => (x + offset_x, y + offset_y),
+        }
+    }
+
+    /// Whether this hitbox, on an entity at (`x`, `y`), overlaps `other` on an entity at
+    /// (`other_x`, `other_y`). Shapes that only touch along an edge don't overlap.
+    pub fn overlaps(self, x: Fixed, y: Fixed, other: Hitbox, other_x: Fixed, other_y: Fixed) -> bool {
+        let (x, y) = self.centre(x, y);
+        let (other_x, other_y) = other.centre(other_x, other_y);
+        match (self, other) {
+            (Hitbox::Rect { half_width, half_height, .. }, Hitbox::Rect { half_width: other_half_width, half_height: other_half_height, .. }) => {
+                (x - other_x).abs() < half_width + other_half_width && (y - other_y).abs() < half_height + other_half_height
+            },
+            (Hitbox::Circle { radius, .. }, Hitbox::Circle { radius: other_radius, .. }) => {
+                let (dx, dy) = (x - other_x, y - other_y);
+                let distance = radius + other_radius;
+                dx * dx + dy * dy < distance * distance
+            },
+            (Hitbox::Rect { half_width, half_height, .. }, Hitbox::Circle { radius, .. }) => {
+                rect_overlaps_circle(x, y, half_width, half_height, other_x, other_y, radius)
+            },
+            (Hitbox::Circle { radius, .. }, Hitbox::Rect { half_width, half_height, .. }) => {
+                rect_overlaps_circle(other_x, other_y, half_width, half_height, x, y, radius)
+            },
+        }
+    }
+
+    /// Outlines the hitbox of an entity at (`x`, `y`) in the current draw colours.
+    pub fn render(self, x: Fixed, y: Fixed, (camera_x, camera_y): (i32, i32)) {
+        let (x, y) = self.centre(x, y);
+        match self {
+            Hitbox::Rect { half_width, half_height, .. } => {
+                let width = (half_width + half_width).to_int().max(1) as u32;
+                let height = (half_height + half_height).to_int().max(1) as u32;
+                rect((x - half_width).to_int() + camera_x, (y - half_height).to_int() + camera_y, width, height);
+            },
+            Hitbox::Circle { radius, .. } => {
+                let diameter = (radius + radius).to_int().max(1) as u32;
+                oval((x - radius).to_int() + camera_x, (y - radius).to_int() + camera_y, diameter, diameter);
+            },
+        }
+    }
+}
+
+// measures from the circle's centre to the nearest point of the rect
+fn rect_overlaps_circle(rect_x: Fixed, rect_y: Fixed, half_width: Fixed, half_height: Fixed, circle_x: Fixed, circle_y: Fixed, radius: Fixed) -> bool {
+    let nearest_x = circle_x.clamp(rect_x - half_width, rect_x + half_width);
+    let nearest_y = circle_y.clamp(rect_y - half_height, rect_y + half_height);
+    let (dx, dy) = (circle_x - nearest_x, circle_y - nearest_y);
+    dx * dx + dy * dy < radius * radius
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: Hitbox = Hitbox::rect(Fixed::new(2), Fixed::new(2));
+    const CIRCLE: Hitbox = Hitbox::circle(Fixed::new(2));
+
+    fn at(x: i32, y: i32) -> (Fixed, Fixed) {
+        (Fixed::new(x), Fixed::new(y))
+    }
+
+    fn overlaps(hitbox: Hitbox, (x, y): (Fixed, Fixed), other: Hitbox, (other_x, other_y): (Fixed, Fixed)) -> bool {
+        let result = hitbox.overlaps(x, y, other, other_x, other_y);
+        assert_eq!(result, other.overlaps(other_x, other_y, hitbox, x, y), "overlap should be symmetric");
+        result
+    }
+
+    #[test]
+    fn rects_overlap_when_their_extents_do() {
+        assert!(overlaps(SQUARE, at(10, 10), SQUARE, at(13, 13)));
+        assert!(!overlaps(SQUARE, at(10, 10), SQUARE, at(14, 10)));
+        assert!(!overlaps(SQUARE, at(10, 10), SQUARE, at(13, 20)));
+    }
+
+    #[test]
+    fn circles_overlap_within_their_combined_radius() {
+        assert!(overlaps(CIRCLE, at(10, 10), CIRCLE, at(12, 12)));
+        assert!(!overlaps(CIRCLE, at(10, 10), CIRCLE, at(14, 10)));
+        // inside the combined bounding boxes, but too far apart diagonally
+        assert!(!overlaps(CIRCLE, at(10, 10), CIRCLE, at(13, 13)));
+    }
+
+    #[test]
+    fn rect_and_circle_overlap_only_near_the_rect() {
+        assert!(overlaps(SQUARE, at(10, 10), CIRCLE, at(13, 10)));
+        assert!(!overlaps(SQUARE, at(10, 10), CIRCLE, at(14, 10)));
+        // the circle misses the rect's corner
+        assert!(!overlaps(SQUARE, at(10, 10), CIRCLE, at(14, 14)));
+        assert!(overlaps(SQUARE, at(10, 10), CIRCLE, at(13, 13)));
+    }
+
+    #[test]
+    fn offsets_move_the_hitbox() {
+        let below = Hitbox::Rect { offset_x: Fixed::ZERO, offset_y: Fixed::new(4), half_width: Fixed::new(2), half_height: Fixed::new(2) };
+        assert!(overlaps(below, at(10, 10), CIRCLE, at(10, 15)));
+        assert!(!overlaps(SQUARE, at(10, 10), CIRCLE, at(10, 15)));
+        let left = Hitbox::Circle { offset_x: Fixed::new(-4), offset_y: Fixed::ZERO, radius: Fixed::new(2) };
+        assert!(overlaps(left, at(10, 10), SQUARE, at(5, 10)));
+        assert!(!overlaps(CIRCLE, at(10, 10), SQUARE, at(5, 10)));
+    }
+
+    #[test]
+    fn sub_pixel_positions_count() {
+        let (x, y) = at(10, 10);
+        let just_touching = x + Fixed::new(4);
+        let just_overlapping = just_touching - Fixed::ratio(1, 256);
+        assert!(!SQUARE.overlaps(x, y, SQUARE, just_touching, y));
+        assert!(SQUARE.overlaps(x, y, SQUARE, just_overlapping, y));
+    }
+}
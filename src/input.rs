@@ -0,0 +1,219 @@
+use crate::wasm4::{BUTTON_1, BUTTON_2, BUTTON_DOWN, BUTTON_LEFT, BUTTON_RIGHT, BUTTON_UP};
+
+/// Something a player can do, whichever button it's bound to. Menus confirm with `Fire` and back out
+/// with `Focus`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Action {
+    Fire,
+    Focus,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+pub const ACTION_COUNT: usize = 6;
+
+pub const ALL_ACTIONS: [Action; ACTION_COUNT] = [Action::Fire, Action::Focus, Action::Up, Action::Down, Action::Left, Action::Right];
+
+// every physical button on a gamepad
+const ALL_BUTTONS: [u8; ACTION_COUNT] = [BUTTON_1, BUTTON_2, BUTTON_UP, BUTTON_DOWN, BUTTON_LEFT, BUTTON_RIGHT];
+
+// a held menu direction starts repeating after this many frames, then every few frames after that
+const REPEAT_DELAY: u16 = 20;
+const REPEAT_INTERVAL: u16 = 5;
+// the most frames an action can be let go of between the two presses of a double tap
+const DOUBLE_TAP_WINDOW: u16 = 12;
+
+impl Action {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Fire => "Fire",
+            Action::Focus => "Focus",
+            Action::Up => "Up",
+            Action::Down => "Down",
+            Action::Left => "Left",
+            Action::Right => "Right",
+        }
+    }
+}
+
+/// The name of a physical button, as labelled on the keyboard.
+pub fn button_name(button: u8) -> &'static str {
+    match button {
+        BUTTON_1 => "X",
+        BUTTON_2 => "Z",
+        BUTTON_UP => "Up",
+        BUTTON_DOWN => "Down",
+        BUTTON_LEFT => "Left",
+        BUTTON_RIGHT => "Right",
+        _ => "?",
+    }
+}
+
+/// Which physical button each action is bound to. Every action has its own button, so rebinding
+/// one to a button that's already taken swaps the two.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Bindings {
+    buttons: [u8; ACTION_COUNT],
+}
+
+impl Bindings {
+    pub const DEFAULT: Bindings = Bindings { buttons: ALL_BUTTONS };
+
+    pub fn button(&self, action: Action) -> u8 {
+        self.buttons[action.index()]
+    }
+
+    pub fn bind(&mut self, action: Action, button: u8) {
+        if let Some(taken) = self.buttons.iter().position(|bound| *bound == button) {
+            self.buttons[taken] = self.buttons[action.index()];
+        }
+        self.buttons[action.index()] = button;
+    }
+
+    pub fn to_bytes(self) -> [u8; ACTION_COUNT] {
+        self.buttons
+    }
+
+    /// Bindings read back from `to_bytes`, unless they don't give every action its own button.
+    pub fn from_bytes(buttons: [u8; ACTION_COUNT]) -> Option<Self> {
+        let valid = ALL_BUTTONS.iter().all(|button| buttons.contains(button));
+        if valid { Some(Bindings { buttons }) } else { None }
+    }
+}
+
+/// One gamepad's actions this frame, and how they've changed since the last.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Input {
+    buttons: u8,
+    last_buttons: u8,
+    // a bit per action, by index
+    last_actions: u8,
+    // frames each action has been held, or 0 if it isn't
+    held_frames: [u16; ACTION_COUNT],
+    // frames each action has been let go of, or 0 while it's held
+    idle_frames: [u16; ACTION_COUNT],
+    // how long each action had been let go of when it was last pressed
+    last_gap: [u16; ACTION_COUNT],
+}
+
+impl Input {
+    pub const fn new() -> Self {
+        Self {
+            buttons: 0,
+            last_buttons: 0,
+            last_actions: 0,
+            held_frames: [0; ACTION_COUNT],
+            idle_frames: [u16::MAX; ACTION_COUNT],
+            last_gap: [u16::MAX; ACTION_COUNT],
+        }
+    }
+
+    /// Takes this frame's gamepad byte, translating its buttons into actions with `bindings`.
+    pub fn update(&mut self, gamepad: u8, bindings: &Bindings) {
+        self.last_buttons = self.buttons;
+        self.buttons = gamepad;
+        self.last_actions = 0;
+        for action in ALL_ACTIONS {
+            if self.held(action) {
+                self.last_actions |= 1 << action.index();
+            }
+            let held = gamepad & bindings.button(action) != 0;
+            let frames = &mut self.held_frames[action.index()];
+            *frames = if held { frames.saturating_add(1) } else { 0 };
+            let idle = &mut self.idle_frames[action.index()];
+            if *frames == 1 {
+                self.last_gap[action.index()] = *idle;
+            }
+            *idle = if held { 0 } else { idle.saturating_add(1) };
+        }
+    }
+
+    pub fn held(&self, action: Action) -> bool {
+        self.held_frames[action.index()] > 0
+    }
+
+    /// How many frames in a row the action has been held for, including this one.
+    pub fn held_for(&self, action: Action) -> u16 {
+        self.held_frames[action.index()]
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.held_frames[action.index()] == 1
+    }
+
+    /// Pressed this frame, shortly after being let go of.
+    pub fn double_tapped(&self, action: Action) -> bool {
+        self.pressed(action) && self.last_gap[action.index()] <= DOUBLE_TAP_WINDOW
+    }
+
+    pub fn released(&self, action: Action) -> bool {
+        self.last_actions & (1 << action.index()) != 0 && !self.held(action)
+    }
+
+    /// Pressed this frame, or held long enough to repeat like a key on a keyboard. For moving
+    /// through menus.
+    pub fn repeated(&self, action: Action) -> bool {
+        let frames = self.held_for(action);
+        frames == 1 || frames >= REPEAT_DELAY && (frames - REPEAT_DELAY).is_multiple_of(REPEAT_INTERVAL)
+    }
+
+    pub fn any_held(&self) -> bool {
+        self.buttons != 0
+    }
+
+    /// The first physical button to go down this frame, whatever it's bound to.
+    pub fn new_button(&self) -> Option<u8> {
+        ALL_BUTTONS.into_iter().find(|button| self.buttons & !self.last_buttons & button != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_presses_releases_and_hold_length() {
+        let mut input = Input::new();
+        input.update(BUTTON_1, &Bindings::DEFAULT);
+        assert!(input.pressed(Action::Fire) && input.held(Action::Fire));
+        input.update(BUTTON_1, &Bindings::DEFAULT);
+        assert!(!input.pressed(Action::Fire));
+        assert_eq!(input.held_for(Action::Fire), 2);
+        input.update(0, &Bindings::DEFAULT);
+        assert!(input.released(Action::Fire) && !input.held(Action::Fire));
+        input.update(0, &Bindings::DEFAULT);
+        assert!(!input.released(Action::Fire));
+    }
+
+    #[test]
+    fn double_taps_need_two_presses_close_together() {
+        let mut input = Input::new();
+        input.update(BUTTON_2, &Bindings::DEFAULT);
+        assert!(input.pressed(Action::Focus) && !input.double_tapped(Action::Focus));
+        input.update(0, &Bindings::DEFAULT);
+        input.update(BUTTON_2, &Bindings::DEFAULT);
+        assert!(input.double_tapped(Action::Focus));
+        for _ in 0..=DOUBLE_TAP_WINDOW {
+            input.update(0, &Bindings::DEFAULT);
+        }
+        input.update(BUTTON_2, &Bindings::DEFAULT);
+        assert!(!input.double_tapped(Action::Focus));
+    }
+
+    #[test]
+    fn rebinding_to_a_taken_button_swaps_them() {
+        let mut bindings = Bindings::DEFAULT;
+        bindings.bind(Action::Fire, BUTTON_2);
+        assert_eq!(bindings.button(Action::Fire), BUTTON_2);
+        assert_eq!(bindings.button(Action::Focus), BUTTON_1);
+        let mut input = Input::new();
+        input.update(BUTTON_2, &bindings);
+        assert!(input.held(Action::Fire) && !input.held(Action::Focus));
+    }
+}
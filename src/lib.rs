@@ -1,19 +1,50 @@
-#[cfg(feature = "buddy-alloc")]
+#[cfg(all(feature = "buddy-alloc", target_arch = "wasm32"))]
 mod alloc;
+#[cfg(feature = "debug")]
+mod debug;
+mod effects;
 mod wasm4;
 mod menu;
+mod mouse;
+mod fixed;
 mod game;
+mod hitbox;
+mod input;
 mod lose;
+mod music;
+mod netplay;
+mod options;
+mod particles;
+mod save;
+mod settings;
+mod sfx;
+mod shop;
 mod sprites;
+mod starfield;
+mod upgrades;
 
 use std::cell::{Cell, RefCell, UnsafeCell};
 use std::iter::Filter;
 use std::slice::{Iter, IterMut};
-use game::GameState;
+#[cfg(feature = "debug")]
+use debug::{ComboMask, DebugOverlay, FrameStepper};
+use effects::ScreenEffects;
+use game::{GameState, MAX_PLAYERS};
+use input::Input;
 use lose::LoseState;
 use menu::MenuState;
+use mouse::Mouse;
+use music::{MusicPlayer, TrackId};
+use options::OptionsState;
+use particles::Particles;
+use save::Save;
+use settings::Controls;
+use sfx::SfxQueue;
+use shop::ShopState;
+use starfield::Starfield;
+use upgrades::Upgrades;
 use wasm4::*;
-use crate::State::{Game, Lose, Menu};
+use crate::State::{Game, Lose, Menu, Options, Shop};
 
 #[rustfmt::skip]
 const SMILEY: [u8; 8] = [
@@ -28,30 +59,169 @@ const SMILEY: [u8; 8] = [
 ];
 
 static mut STATE: State = Menu(MenuState::new());
-static mut LAST_GAMEPAD: u8 = 0;
+static mut INPUTS: [Input; MAX_PLAYERS] = [Input::new(); MAX_PLAYERS];
+static mut MOUSE: Mouse = Mouse::new();
+// the same again, but only updated on frames the game actually runs
+static mut GAME_INPUTS: [Input; MAX_PLAYERS] = [Input::new(); MAX_PLAYERS];
+static mut GAME_MOUSE: Mouse = Mouse::new();
+static mut MUSIC: MusicPlayer = MusicPlayer::new();
+static mut SFX: SfxQueue = SfxQueue::new();
+static mut SAVE: Save = Save::new();
+static mut PARTICLES: Particles = Particles::new();
+static mut STARFIELD: Starfield = Starfield::new();
+static mut EFFECTS: ScreenEffects = ScreenEffects::new();
+#[cfg(feature = "debug")]
+static mut DEBUG_OVERLAY: DebugOverlay = DebugOverlay::new();
+#[cfg(feature = "debug")]
+static mut FRAME_STEPPER: FrameStepper = FrameStepper::new();
+#[cfg(feature = "debug")]
+static mut COMBO_MASK: ComboMask = ComboMask::new();
+
+#[no_mangle]
+fn start() {
+    unsafe { SAVE = Save::load() };
+}
 
 #[no_mangle]
 fn update() {
-    let gamepad = unsafe { *GAMEPAD1 };
-    let last_gamepad = unsafe { LAST_GAMEPAD };
+    let gamepads = unsafe { [*GAMEPAD1, *GAMEPAD2, *GAMEPAD3, *GAMEPAD4] };
+    let mut save = unsafe { SAVE };
+    let saved = save;
+    let mut settings = save.settings;
+    let gameplay_settings = if netplay::active() { settings.shared() } else { settings };
+    let mut inputs = unsafe { INPUTS };
+    for (input, gamepad) in inputs.iter_mut().zip(gamepads) {
+        input.update(gamepad, &gameplay_settings.bindings);
+    }
+    // menus and the like are only driven by the first gamepad
+    let input = &inputs[0];
+    let mut mouse = unsafe { MOUSE };
+    mouse.update();
+    // the mouse isn't shared with the other carts during netplay, so it would put them out of sync
+    let local_mouse = if netplay::active() { None } else { Some(mouse) };
+    let mut game_inputs = unsafe { GAME_INPUTS };
+    let mut game_mouse = unsafe { GAME_MOUSE };
+    let mut sfx = unsafe { SFX };
+    let mut particles = unsafe { PARTICLES };
+    let mut starfield = unsafe { STARFIELD };
+    let mut effects = unsafe { EFFECTS };
+    #[cfg(feature = "debug")]
+    let mut debug_overlay = unsafe { DEBUG_OVERLAY };
+    #[cfg(feature = "debug")]
+    debug_overlay.update(input);
+    #[cfg(feature = "debug")]
+    let mut frame_stepper = unsafe { FRAME_STEPPER };
+    #[cfg(feature = "debug")]
+    frame_stepper.update(input);
+    #[cfg(feature = "debug")]
+    let advance_game = frame_stepper.advance();
+    #[cfg(feature = "debug")]
+    let mut combo_mask = unsafe { COMBO_MASK };
+    #[cfg(feature = "debug")]
+    combo_mask.update(input);
     let state = match unsafe { STATE } {
-        Menu(state) => menu::update_menu(state, gamepad, last_gamepad),
-        Game(state) => game::update_game(state, gamepad, last_gamepad),
-        Lose(state) => lose::update_lose(state, gamepad, last_gamepad),
+        // every cart in a netplay session has to start from the same game, whatever they've bought
+        Menu(state) => menu::update_menu(state, &inputs, local_mouse, if netplay::active() { Upgrades::NONE } else { save.upgrades }),
+        // during netplay every cart's menus follow player 1's gamepad, so they can't be allowed to
+        // change this cart's own settings or spend its coins
+        Options(state) if netplay::active() => options::update_options(state, &mut { settings }, input),
+        Options(state) => options::update_options(state, &mut settings, input),
+        Shop(state) if netplay::active() => shop::update_shop(state, &mut { save }, input),
+        Shop(state) => shop::update_shop(state, &mut save, input),
+        Game(state) if effects.frozen() => Game(state),
+        #[cfg(feature = "debug")]
+        Game(state) if !advance_game => Game(state),
+        Game(state) => {
+            // presses are measured between the frames the game runs, so hit-stop and slow motion
+            // can't swallow one
+            #[cfg(feature = "debug")]
+            let gamepads = combo_mask.mask(gamepads);
+            for (input, gamepad) in game_inputs.iter_mut().zip(gamepads) {
+                input.update(gamepad, &gameplay_settings.bindings);
+            }
+            game_mouse.update();
+            let mouse = Some(game_mouse).filter(|_| !netplay::active() && gameplay_settings.controls == Controls::Mouse);
+            let (state, outputs) = game::update_game(state, &game_inputs, mouse, &gameplay_settings);
+            save.coins = save.coins.saturating_add(game::coins_collected(&outputs));
+            if let (true, Game(state)) = (netplay::active(), &state) {
+                game::trace_checksum(state);
+            }
+            game::request_game_sounds(&outputs, &mut sfx);
+            game::trigger_screen_effects(&outputs, &mut effects);
+            if settings.particles {
+                game::spawn_game_particles(&state, &outputs, &mut particles);
+            }
+            state
+        },
+        Lose(state) => lose::update_lose(state, input, local_mouse),
     };
     match state {
-        Menu(state) => menu::render_menu(state),
-        Game(state) => game::render_game(state),
+        Menu(state) => menu::render_menu(state, save.coins, netplay::local_player()),
+        Options(state) => options::render_options(state, &settings),
+        Shop(state) => shop::render_shop(state, &save),
+        Game(state) => {
+            let camera = effects.camera(settings.screen_shake);
+            starfield.render(settings.nebula);
+            particles.render(camera);
+            game::render_game(state, camera, netplay::local_player());
+            #[cfg(feature = "debug")]
+            debug_overlay.render(&state, camera);
+            #[cfg(feature = "debug")]
+            frame_stepper.render();
+        },
         Lose(state) => lose::render_lose(state),
     }
+    if !settings.particles || !matches!(state, Game(_)) {
+        particles.clear();
+    }
+    if !matches!(state, Game(_)) {
+        effects.clear();
+    }
+    effects.update();
+    particles.update();
+    if let Game(state) = state {
+        starfield.update(state.player_dx(), state.boss_active());
+    }
+
+    let mut music = unsafe { MUSIC };
+    music.play(match state {
+        Menu(_) | Options(_) | Shop(_) => TrackId::Menu,
+        Game(state) if state.boss_active() => TrackId::Boss,
+        Game(_) => TrackId::Gameplay,
+        Lose(_) => TrackId::GameOver,
+    });
+    music.hurry(matches!(state, Game(state) if state.boss_nearly_beaten()));
+    sfx.flush(music.reserved_channels(), &settings);
+    music.update(sfx.busy_channels(), settings.master_volume);
+    unsafe { MUSIC = music };
+    unsafe { SFX = sfx };
+    save.settings = settings;
+    if save != saved {
+        save.write();
+    }
+    unsafe { SAVE = save };
+    unsafe { PARTICLES = particles };
+    unsafe { STARFIELD = starfield };
+    unsafe { EFFECTS = effects };
+    #[cfg(feature = "debug")]
+    unsafe { DEBUG_OVERLAY = debug_overlay };
+    #[cfg(feature = "debug")]
+    unsafe { FRAME_STEPPER = frame_stepper };
+    #[cfg(feature = "debug")]
+    unsafe { COMBO_MASK = combo_mask };
 
     unsafe { STATE = state };
-    unsafe { LAST_GAMEPAD = gamepad };
+    unsafe { INPUTS = inputs };
+    unsafe { MOUSE = mouse };
+    unsafe { GAME_INPUTS = game_inputs };
+    unsafe { GAME_MOUSE = game_mouse };
 }
 
 #[derive(Copy, Clone)]
 pub enum State {
     Menu(MenuState),
+    Options(OptionsState),
+    Shop(ShopState),
     Game(GameState),
     Lose(LoseState)
 }
@@ -1,29 +1,81 @@
-use crate::menu::MenuState;
+use crate::game::{CONTINUE_PENALTY, GameState, MAX_PLAYERS};
+use crate::input::{Action, Input};
+use crate::menu::{MenuState, set_item_colours};
+use crate::mouse::Mouse;
 use crate::State;
-use crate::State::{Lose, Menu};
-use crate::wasm4::{BUTTON_1, DRAW_COLORS, text};
+use crate::State::{Game, Lose, Menu};
+use crate::wasm4::{DRAW_COLORS, MOUSE_LEFT, text};
 
 #[derive(Copy, Clone)]
 pub struct LoseState {
-    score: u32,
+    // for each player who took part
+    scores: [Option<u32>; MAX_PLAYERS],
+    outcome: Outcome,
+    // the game as it ended, to pick back up from if the players continue
+    continue_from: Option<GameState>,
+    selected: u8,
     pressed: bool,
 }
 
+// the top of each item on screen, which the mouse can point at
+const CONTINUE_Y: i32 = 70;
+const MAIN_MENU_Y: i32 = 80;
+
+/// How a game ended: everyone ran out of health together, or one player outlasted the other in
+/// versus.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Outcome {
+    GameOver,
+    Winner(usize),
+    Draw,
+}
+
 impl LoseState {
-    pub fn new(score: u32) -> Self {
+    pub fn new(scores: [Option<u32>; MAX_PLAYERS], outcome: Outcome, continue_from: Option<GameState>) -> Self {
         Self {
-            score,
+            scores,
+            outcome,
+            continue_from,
+            selected: 0,
             pressed: false,
         }
     }
+
+    // continuing comes first when it's on offer, so the main menu is always the last item
+    fn item_count(&self) -> u8 {
+        if self.continue_from.is_some() { 2 } else { 1 }
+    }
+
+    fn item_y(&self, item: u8) -> i32 {
+        if item + 1 == self.item_count() { MAIN_MENU_Y } else { CONTINUE_Y }
+    }
 }
 
-pub fn update_lose(state: LoseState, gamepad: u8, last_gamepad: u8) -> State {
+pub fn update_lose(state: LoseState, input: &Input, mouse: Option<Mouse>) -> State {
     let mut new_state = state;
-    if state.pressed && gamepad & BUTTON_1 == 0 {
-        Menu(MenuState::new())
+    let item_count = state.item_count();
+    if input.repeated(Action::Up) {
+        new_state.selected = (new_state.selected + item_count - 1) % item_count;
+    }
+    if input.repeated(Action::Down) {
+        new_state.selected = (new_state.selected + 1) % item_count;
+    }
+    let hovered = mouse.and_then(|mouse| (0..item_count).find(|item| mouse.over(10, state.item_y(*item), 140, 8)));
+    if let (Some(item), Some(mouse)) = (hovered, mouse) {
+        if mouse.moved() || mouse.pressed(MOUSE_LEFT) {
+            new_state.selected = item;
+        }
+    }
+    let clicked = hovered.is_some() && mouse.is_some_and(|mouse| mouse.pressed(MOUSE_LEFT));
+    let mouse_held = mouse.is_some_and(|mouse| mouse.held(MOUSE_LEFT));
+    if state.pressed && !input.held(Action::Fire) && !mouse_held {
+        match state.continue_from {
+            Some(game) if state.selected == 0 => Game(game.continued()),
+            _ => Menu(MenuState::new()),
+        }
     } else {
-        if gamepad & BUTTON_1 != 0 {
+        // only a fresh press counts, so fire held down as the game ended can't continue by itself
+        if input.pressed(Action::Fire) || clicked {
             new_state.pressed = true;
         }
 
@@ -33,10 +85,62 @@ pub fn update_lose(state: LoseState, gamepad: u8, last_gamepad: u8) -> State {
 
 pub fn render_lose(state: LoseState) {
     unsafe { *DRAW_COLORS = 0x0003 }
-    text("GAME OVER", 10, 10);
-    text(format!("Score: {}", state.score).as_str(), 10, 20);
+    match state.outcome {
+        Outcome::GameOver => text("GAME OVER", 10, 10),
+        Outcome::Winner(player) => text(format!("P{} WINS", player + 1).as_str(), 10, 10),
+        Outcome::Draw => text("DRAW", 10, 10),
+    }
+    let scores: Vec<(usize, u32)> = state.scores.iter().enumerate().filter_map(|(player, score)| score.map(|score| (player, score))).collect();
+    if let [(_, score)] = scores[..] {
+        text(format!("Score: {}", score).as_str(), 10, 20);
+    } else {
+        for (line, (player, score)) in scores.iter().enumerate() {
+            text(format!("P{}: {}", player + 1, score).as_str(), 10, 20 + line as i32 * 8);
+        }
+        // co-op teams also get a total; in versus there's only the winner
+        if state.outcome == Outcome::GameOver {
+            let total: u32 = scores.iter().map(|(_, score)| score).sum();
+            text(format!("Team: {}", total).as_str(), 10, 24 + scores.len() as i32 * 8);
+        }
+    }
+
+    if state.continue_from.is_some() {
+        set_item_colours(0, state.selected, state.pressed);
+        text(format!("Continue (-{})", CONTINUE_PENALTY).as_str(), 10, CONTINUE_Y);
+    }
+    set_item_colours(state.item_count() - 1, state.selected, state.pressed);
+    text("Main Menu", 10, MAIN_MENU_Y);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Lives, Mode};
+    use crate::input::Bindings;
+    use crate::upgrades::Upgrades;
+    use crate::wasm4::BUTTON_1;
+
+    fn update(state: State, input: &mut Input, gamepad: u8) -> State {
+        input.update(gamepad, &Bindings::DEFAULT);
+        match state {
+            Lose(state) => update_lose(state, input, None),
+            state => state,
+        }
+    }
 
-    unsafe { *DRAW_COLORS = if state.pressed { 0x0002 } else { 0x0004 } }
-    text("Press X to return", 10, 70);
-    text("to Main Menu", 10, 80);
+    #[test]
+    fn fire_held_over_from_the_game_does_not_continue() {
+        let game = GameState::new(7, [true, false, false, false], Lives::Shared, Mode::Coop, Upgrades::NONE);
+        let mut state = Lose(LoseState::new([Some(0), None, None, None], Outcome::GameOver, Some(game)));
+        let mut input = Input::new();
+        // still holding fire from before the game ended
+        input.update(BUTTON_1, &Bindings::DEFAULT);
+        state = update(state, &mut input, BUTTON_1);
+        state = update(state, &mut input, 0);
+        assert!(matches!(state, Lose(_)));
+        // a deliberate press does
+        state = update(state, &mut input, BUTTON_1);
+        state = update(state, &mut input, 0);
+        assert!(matches!(state, Game(_)));
+    }
 }
@@ -1,14 +1,27 @@
-use crate::game::GameState;
+use crate::game::{GameState, Lives, MAX_PLAYERS, Mode};
+use crate::input::{Action, Input};
+use crate::mouse::Mouse;
+use crate::options::OptionsState;
+use crate::shop::ShopState;
 use crate::sprites::{render_eye, render_powerup, render_ship};
 use crate::State;
-use crate::State::{Game, Menu};
-use crate::wasm4::{BUTTON_1, BUTTON_DOWN, BUTTON_UP, DRAW_COLORS, text};
+use crate::State::{Game, Menu, Options, Shop};
+use crate::upgrades::Upgrades;
+use crate::wasm4::{DRAW_COLORS, MOUSE_LEFT, text};
+
+const ITEM_COUNT: u8 = 6;
+// the top and height of each item on screen, for pointing at them with the mouse
+const ITEM_ROWS: [(i32, i32); ITEM_COUNT as usize] = [(30, 8), (40, 18), (60, 8), (70, 8), (80, 8), (90, 8)];
 
 #[derive(Copy, Clone)]
 pub struct MenuState {
     selected: u8,
     pressed: bool,
-    difficulty: Difficulty
+    difficulty: Difficulty,
+    lives: Lives,
+    mode: Mode,
+    // player 1 is always in; the others join from their own gamepads
+    joined: [bool; MAX_PLAYERS],
 }
 
 #[derive(Copy, Clone)]
@@ -57,49 +70,121 @@ impl MenuState {
         Self {
             selected: 0,
             pressed: false,
-            difficulty: Difficulty::Normal
+            difficulty: Difficulty::Normal,
+            lives: Lives::Shared,
+            mode: Mode::Coop,
+            joined: [true, false, false, false],
         }
     }
 }
 
-pub fn update_menu(state: MenuState, gamepad: u8, last_gamepad: u8) -> State {
+fn item_under(mouse: Mouse) -> Option<u8> {
+    ITEM_ROWS.iter().position(|(top, height)| mouse.over(10, *top, 140, *height)).map(|item| item as u8)
+}
+
+/// Runs the menu, starting any game with `upgrades` bought from the shop.
+pub fn update_menu(state: MenuState, inputs: &[Input; MAX_PLAYERS], mouse: Option<Mouse>, upgrades: Upgrades) -> State {
     let mut new_state = state;
-    if gamepad & !last_gamepad & BUTTON_UP != 0 {
-        new_state.selected = new_state.selected.wrapping_sub(1) % 2;
+    for (player, input) in inputs.iter().enumerate().skip(1) {
+        if input.pressed(Action::Fire) {
+            new_state.joined[player] = true;
+        } else if input.pressed(Action::Focus) {
+            new_state.joined[player] = false;
+        }
     }
-    if gamepad & !last_gamepad & BUTTON_DOWN != 0 {
-        new_state.selected = new_state.selected.wrapping_add(1) % 2;
+
+    let input = &inputs[0];
+    if input.repeated(Action::Up) {
+        new_state.selected = (new_state.selected + ITEM_COUNT - 1) % ITEM_COUNT;
     }
-    if gamepad & BUTTON_1 != 0 {
+    if input.repeated(Action::Down) {
+        new_state.selected = (new_state.selected + 1) % ITEM_COUNT;
+    }
+    // pointing at an item selects it, but only once the mouse moves so it doesn't fight the d-pad
+    let hovered = mouse.and_then(item_under);
+    if let (Some(item), Some(mouse)) = (hovered, mouse) {
+        if mouse.moved() || mouse.pressed(MOUSE_LEFT) {
+            new_state.selected = item;
+        }
+    }
+    let on_selected = hovered == Some(new_state.selected);
+    let clicking = on_selected && mouse.is_some_and(|mouse| mouse.held(MOUSE_LEFT));
+    let clicked = on_selected && mouse.is_some_and(|mouse| mouse.released(MOUSE_LEFT));
+    if input.held(Action::Fire) || clicking {
         new_state.pressed = true;
-    } else if last_gamepad & BUTTON_1 != 0 {
+    } else if input.released(Action::Fire) || clicked {
         if new_state.selected == 0 {
-            return Game(GameState::new(new_state.difficulty.to_difficulty_level()))
+            return Game(GameState::new(new_state.difficulty.to_difficulty_level(), new_state.joined, new_state.lives, new_state.mode, upgrades))
         } else if new_state.selected == 1 {
             new_state.difficulty = new_state.difficulty.next();
+        } else if new_state.selected == 2 {
+            new_state.lives = new_state.lives.next();
+        } else if new_state.selected == 3 {
+            new_state.mode = new_state.mode.next();
         }
         new_state.pressed = false;
+        if new_state.selected == 4 {
+            return Shop(ShopState::new(new_state))
+        } else if new_state.selected == 5 {
+            return Options(OptionsState::new(new_state))
+        }
+    } else {
+        // the mouse was dragged off before letting go
+        new_state.pressed = false;
     }
     Menu(new_state)
 }
 
-pub fn render_menu(state: MenuState) {
+/// Picks the colours for a menu item: highlighted when it's selected, and darker while it's being
+/// pressed. Shared by every screen with a list to pick from.
+pub fn set_item_colours(item: u8, selected: u8, pressed: bool) {
+    unsafe { *DRAW_COLORS = if selected == item { if pressed { 0x0002 } else { 0x0004 } } else { 0x0003 } }
+}
+
+/// Draws the menu, along with the coins banked from every run so far.
+pub fn render_menu(state: MenuState, coins: u32, local_player: Option<usize>) {
     unsafe { *DRAW_COLORS = 0x0003 }
     text("W4-SHOT", 10, 10);
-    unsafe { *DRAW_COLORS = if state.selected == 0 { if state.pressed { 0x0002 } else { 0x0004 } } else { 0x0003 } }
+    let coins = format!("${}", coins);
+    text(coins.as_str(), 150 - 8 * coins.len() as i32, 10);
+    set_item_colours(0, state.selected, state.pressed);
     text("Play", 10, 30);
     unsafe { *DRAW_COLORS = 0x0003 }
     text("Difficulty: ", 10, 40);
-    unsafe { *DRAW_COLORS = if state.selected == 1 { if state.pressed { 0x0002 } else { 0x0004 } } else { 0x0003 } }
+    set_item_colours(1, state.selected, state.pressed);
     text(state.difficulty.to_str(), 17, 50);
+    set_item_colours(2, state.selected, state.pressed);
+    text(format!("Lives: {}", state.lives.name()).as_str(), 10, 60);
+    set_item_colours(3, state.selected, state.pressed);
+    text(format!("Mode: {}", state.mode.name()).as_str(), 10, 70);
+    set_item_colours(4, state.selected, state.pressed);
+    text("Shop", 10, 80);
+    set_item_colours(5, state.selected, state.pressed);
+    text("Options", 10, 90);
 
     unsafe { *DRAW_COLORS = 0x0003 }
-    text("The enemy:", 40, 80);
-    text("      You:", 40, 100);
-    text("  Powerup:", 40, 120);
+    text("The enemy:", 40, 102);
+    text("      You:", 40, 114);
+    text("  Powerup:", 40, 126);
     unsafe { *DRAW_COLORS = 0x2430 }
-    render_ship(130, 100);
+    render_ship(130, 114);
     unsafe { *DRAW_COLORS = 0x0432 }
-    render_eye(130, 80);
-    render_powerup(130, 120);
+    render_eye(130, 102);
+    render_powerup(130, 126);
+
+    if state.mode == Mode::Versus {
+        unsafe { *DRAW_COLORS = 0x0003 }
+        text("P1 vs P2", 10, 136);
+        text("Pads 1 and 2 face off", 2, 148);
+        return;
+    }
+    for (player, joined) in state.joined.iter().enumerate() {
+        unsafe { *DRAW_COLORS = if local_player == Some(player) { 0x0004 } else { 0x0003 } }
+        text(if *joined { format!("P{}", player + 1) } else { String::from("--") }.as_str(), 10 + player as i32 * 24, 136);
+    }
+    unsafe { *DRAW_COLORS = 0x0003 }
+    match local_player {
+        Some(player) => text(format!("Netplay: you're P{}", player + 1).as_str(), 2, 148),
+        None => text("X on pads 2-4 joins", 2, 148),
+    }
 }
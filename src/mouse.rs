@@ -0,0 +1,59 @@
+use crate::wasm4::{MOUSE_BUTTONS, MOUSE_X, MOUSE_Y};
+
+/// The mouse this frame, along with where it was and what was held the frame before, so that
+/// movement and clicks can be told apart from the mouse just resting somewhere.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Mouse {
+    pub x: i32,
+    pub y: i32,
+    last_x: i32,
+    last_y: i32,
+    buttons: u8,
+    last_buttons: u8,
+}
+
+impl Mouse {
+    pub const fn new() -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            last_x: 0,
+            last_y: 0,
+            buttons: 0,
+            last_buttons: 0,
+        }
+    }
+
+    /// Reads the mouse for this frame, remembering last frame's position and buttons.
+    pub fn update(&mut self) {
+        self.last_x = self.x;
+        self.last_y = self.y;
+        self.last_buttons = self.buttons;
+        unsafe {
+            self.x = *MOUSE_X as i32;
+            self.y = *MOUSE_Y as i32;
+            self.buttons = *MOUSE_BUTTONS;
+        }
+    }
+
+    pub fn moved(&self) -> bool {
+        self.x != self.last_x || self.y != self.last_y
+    }
+
+    pub fn held(&self, button: u8) -> bool {
+        self.buttons & button != 0
+    }
+
+    pub fn pressed(&self, button: u8) -> bool {
+        self.buttons & !self.last_buttons & button != 0
+    }
+
+    pub fn released(&self, button: u8) -> bool {
+        !self.buttons & self.last_buttons & button != 0
+    }
+
+    /// Whether the pointer is over the rectangle with its top left corner at (`x`, `y`).
+    pub fn over(&self, x: i32, y: i32, width: i32, height: i32) -> bool {
+        self.x >= x && self.x < x + width && self.y >= y && self.y < y + height
+    }
+}
@@ -0,0 +1,299 @@
+use crate::wasm4::{tone, TONE_MODE2, TONE_MODE3, TONE_NOISE, TONE_PULSE2, TONE_TRIANGLE};
+
+// Notes are stored as semitones above C0, offset by one so that 0 can mean a rest.
+#[derive(Copy, Clone)]
+struct Note {
+    pitch: u8,
+    ticks: u8,
+}
+
+const C: u8 = 0;
+const CS: u8 = 1;
+const D: u8 = 2;
+const E: u8 = 4;
+const F: u8 = 5;
+const FS: u8 = 6;
+const G: u8 = 7;
+const GS: u8 = 8;
+const A: u8 = 9;
+const AS: u8 = 10;
+const B: u8 = 11;
+
+const fn note(semitone: u8, octave: u8, ticks: u8) -> Note {
+    Note { pitch: 1 + octave * 12 + semitone, ticks }
+}
+
+const fn rest(ticks: u8) -> Note {
+    Note { pitch: 0, ticks }
+}
+
+// frequencies of the eighth octave, halved once for every octave below
+const OCTAVE_8: [u32; 12] = [4186, 4435, 4699, 4978, 5274, 5588, 5920, 6272, 6645, 7040, 7459, 7902];
+
+fn frequency(pitch: u8) -> u32 {
+    let semitones = pitch - 1;
+    OCTAVE_8[(semitones % 12) as usize] >> (8 - (semitones / 12).min(8))
+}
+
+struct Voice {
+    flags: u32,
+    volume: u32,
+    // longest a note is held for, in frames; 0 holds it for its full length
+    gate: u8,
+    // reserved voices are never interrupted by sound effects
+    reserved: bool,
+    patterns: &'static [&'static [Note]],
+}
+
+impl Voice {
+    fn channel(&self) -> u32 {
+        self.flags & 0b11
+    }
+
+    fn play(&self, note: Note, tempo: u8, master_volume: u8) {
+        if note.pitch == 0 {
+            return;
+        }
+        let length = note.ticks as u32 * tempo as u32;
+        let sustain = if self.gate == 0 { length.saturating_sub(2) } else { length.min(self.gate as u32) };
+        tone(frequency(note.pitch), sustain | (2 << 8), self.volume * master_volume as u32 / 100, self.flags);
+    }
+}
+
+struct Track {
+    // frames per tick
+    tempo: u8,
+    looping: bool,
+    voices: &'static [Voice],
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum TrackId {
+    Menu,
+    Gameplay,
+    Boss,
+    GameOver,
+}
+
+impl TrackId {
+    fn track(self) -> &'static Track {
+        match self {
+            TrackId::Menu => &MENU_TRACK,
+            TrackId::Gameplay => &GAMEPLAY_TRACK,
+            TrackId::Boss => &BOSS_TRACK,
+            TrackId::GameOver => &GAME_OVER_TRACK,
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+struct VoiceState {
+    pattern: u8,
+    note: u8,
+    ticks_left: u8,
+    finished: bool,
+}
+
+const VOICE_START: VoiceState = VoiceState {
+    pattern: 0,
+    note: 0,
+    ticks_left: 0,
+    finished: false,
+};
+
+impl VoiceState {
+    fn tick(&mut self, voice: &Voice, tempo: u8, looping: bool, muted: bool, master_volume: u8) {
+        if self.finished {
+            return;
+        }
+        if self.ticks_left == 0 {
+            let note = voice.patterns[self.pattern as usize][self.note as usize];
+            if !muted {
+                voice.play(note, tempo, master_volume);
+            }
+            self.ticks_left = note.ticks;
+            self.advance(voice, looping);
+        }
+        self.ticks_left = self.ticks_left.saturating_sub(1);
+    }
+
+    fn advance(&mut self, voice: &Voice, looping: bool) {
+        self.note += 1;
+        if self.note as usize >= voice.patterns[self.pattern as usize].len() {
+            self.note = 0;
+            self.pattern += 1;
+        }
+        if self.pattern as usize >= voice.patterns.len() {
+            self.pattern = 0;
+            self.finished = !looping;
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct MusicPlayer {
+    track: Option<TrackId>,
+    tempo: u8,
+    frames_until_tick: u8,
+    voices: [VoiceState; 4],
+}
+
+impl MusicPlayer {
+    pub const fn new() -> Self {
+        Self {
+            track: None,
+            tempo: 1,
+            frames_until_tick: 0,
+            voices: [VOICE_START; 4],
+        }
+    }
+
+    /// Starts playing `track` from the beginning, unless it is already playing.
+    pub fn play(&mut self, track: TrackId) {
+        if self.track == Some(track) {
+            return;
+        }
+        self.track = Some(track);
+        self.tempo = track.track().tempo;
+        self.frames_until_tick = 0;
+        self.voices = [VOICE_START; 4];
+    }
+
+    /// Plays the current track a frame per tick faster while `hurried`, from its next tick. A newly
+    /// started track is back at its own tempo.
+    pub fn hurry(&mut self, hurried: bool) {
+        let Some(track_id) = self.track else { return };
+        let tempo = track_id.track().tempo;
+        self.tempo = if hurried { (tempo - 1).max(1) } else { tempo };
+    }
+
+    /// Bitmask of the channels that sound effects must leave alone.
+    pub fn reserved_channels(&self) -> u8 {
+        let Some(track_id) = self.track else { return 0 };
+        track_id.track().voices.iter()
+            .filter(|voice| voice.reserved)
+            .fold(0, |mask, voice| mask | 1 << voice.channel())
+    }
+
+    /// Advances the current track by a frame. Notes due on a channel in `busy_channels` are skipped
+    /// so that they don't cut off a sound effect.
+    pub fn update(&mut self, busy_channels: u8, master_volume: u8) {
+        let Some(track_id) = self.track else { return };
+        if self.frames_until_tick > 0 {
+            self.frames_until_tick -= 1;
+            return;
+        }
+        self.frames_until_tick = self.tempo - 1;
+        let track = track_id.track();
+        for (voice, state) in track.voices.iter().zip(self.voices.iter_mut()) {
+            let muted = busy_channels & (1 << voice.channel()) != 0;
+            state.tick(voice, self.tempo, track.looping, muted, master_volume);
+        }
+    }
+}
+
+const HAT: Note = note(C, 8, 2);
+const SNARE: Note = note(C, 6, 2);
+const KICK: Note = note(C, 3, 2);
+
+const DRUMS: &[Note] = &[KICK, HAT, SNARE, HAT];
+
+// menu: a slow arpeggio over Am - F - C - G
+const MENU_LEAD_A: &[Note] = &[
+    note(A, 4, 2), note(C, 5, 2), note(E, 5, 2), note(C, 5, 2),
+    note(F, 4, 2), note(A, 4, 2), note(C, 5, 2), note(A, 4, 2),
+    note(C, 5, 2), note(E, 5, 2), note(G, 5, 2), note(E, 5, 2),
+    note(B, 4, 2), note(D, 5, 2), note(G, 5, 2), note(D, 5, 2),
+];
+const MENU_LEAD_B: &[Note] = &[
+    note(E, 5, 4), note(D, 5, 2), note(C, 5, 2),
+    note(A, 4, 6), rest(2),
+    note(G, 4, 2), note(A, 4, 2), note(C, 5, 2), note(E, 5, 2),
+    note(D, 5, 6), rest(2),
+];
+const MENU_BASS: &[Note] = &[note(A, 2, 8), note(F, 2, 8), note(C, 3, 8), note(G, 2, 8)];
+
+const MENU_TRACK: Track = Track {
+    tempo: 9,
+    looping: true,
+    voices: &[
+        Voice { flags: TONE_PULSE2 | TONE_MODE2, volume: 20, gate: 0, reserved: true, patterns: &[MENU_LEAD_A, MENU_LEAD_B] },
+        Voice { flags: TONE_TRIANGLE, volume: 30, gate: 0, reserved: false, patterns: &[MENU_BASS, MENU_BASS] },
+    ],
+};
+
+// gameplay: a driving line in E minor
+const GAMEPLAY_LEAD_A: &[Note] = &[
+    note(E, 5, 2), note(E, 5, 1), note(D, 5, 1), note(E, 5, 2), note(G, 5, 2),
+    note(FS, 5, 2), note(E, 5, 2), note(D, 5, 2), note(B, 4, 2),
+    note(C, 5, 2), note(C, 5, 1), note(B, 4, 1), note(C, 5, 2), note(E, 5, 2),
+    note(D, 5, 4), note(B, 4, 4),
+];
+const GAMEPLAY_LEAD_B: &[Note] = &[
+    note(E, 5, 2), note(G, 5, 2), note(B, 5, 2), note(A, 5, 2),
+    note(G, 5, 2), note(FS, 5, 2), note(E, 5, 4),
+    note(C, 5, 2), note(D, 5, 2), note(E, 5, 2), note(D, 5, 2),
+    note(B, 4, 8),
+];
+const GAMEPLAY_BASS: &[Note] = &[
+    note(E, 2, 2), note(E, 3, 2), note(E, 2, 2), note(E, 3, 2),
+    note(E, 2, 2), note(E, 3, 2), note(D, 2, 2), note(D, 3, 2),
+    note(C, 2, 2), note(C, 3, 2), note(C, 2, 2), note(C, 3, 2),
+    note(D, 2, 2), note(D, 3, 2), note(B, 1, 2), note(B, 2, 2),
+];
+
+const GAMEPLAY_TRACK: Track = Track {
+    tempo: 7,
+    looping: true,
+    voices: &[
+        Voice { flags: TONE_PULSE2 | TONE_MODE2, volume: 20, gate: 0, reserved: true, patterns: &[GAMEPLAY_LEAD_A, GAMEPLAY_LEAD_B] },
+        Voice { flags: TONE_TRIANGLE, volume: 30, gate: 0, reserved: false, patterns: &[GAMEPLAY_BASS, GAMEPLAY_BASS] },
+        Voice { flags: TONE_NOISE, volume: 8, gate: 2, reserved: false, patterns: &[DRUMS, DRUMS, DRUMS, DRUMS, DRUMS, DRUMS, DRUMS, DRUMS] },
+    ],
+};
+
+// boss: chromatic and insistent, in D minor
+const BOSS_LEAD_A: &[Note] = &[
+    note(D, 5, 2), rest(2), note(D, 5, 2), note(F, 5, 2),
+    note(E, 5, 2), rest(2), note(E, 5, 2), note(CS, 5, 2),
+    note(D, 5, 2), rest(2), note(D, 5, 2), note(A, 5, 2),
+    note(GS, 5, 4), note(A, 5, 4),
+];
+const BOSS_LEAD_B: &[Note] = &[
+    note(AS, 4, 2), note(A, 4, 2), note(AS, 4, 2), note(D, 5, 2),
+    note(C, 5, 2), note(AS, 4, 2), note(A, 4, 4),
+    note(G, 4, 2), note(A, 4, 2), note(AS, 4, 2), note(CS, 5, 2),
+    note(D, 5, 8),
+];
+const BOSS_BASS: &[Note] = &[
+    note(D, 2, 2), note(D, 2, 2), note(D, 3, 2), note(D, 2, 2),
+    note(AS, 1, 2), note(AS, 1, 2), note(AS, 2, 2), note(AS, 1, 2),
+    note(G, 1, 2), note(G, 1, 2), note(G, 2, 2), note(G, 1, 2),
+    note(A, 1, 2), note(A, 1, 2), note(A, 2, 2), note(CS, 2, 2),
+];
+
+const BOSS_TRACK: Track = Track {
+    tempo: 6,
+    looping: true,
+    voices: &[
+        Voice { flags: TONE_PULSE2 | TONE_MODE3, volume: 22, gate: 0, reserved: true, patterns: &[BOSS_LEAD_A, BOSS_LEAD_B] },
+        Voice { flags: TONE_TRIANGLE, volume: 35, gate: 0, reserved: false, patterns: &[BOSS_BASS, BOSS_BASS] },
+        Voice { flags: TONE_NOISE, volume: 10, gate: 2, reserved: false, patterns: &[DRUMS, DRUMS, DRUMS, DRUMS, DRUMS, DRUMS, DRUMS, DRUMS] },
+    ],
+};
+
+// game over: a short falling phrase that doesn't repeat
+const GAME_OVER_LEAD: &[Note] = &[
+    note(A, 4, 2), note(G, 4, 2), note(F, 4, 2), note(E, 4, 2),
+    note(D, 4, 4), note(CS, 4, 4), note(D, 4, 8),
+];
+const GAME_OVER_BASS: &[Note] = &[note(D, 3, 8), note(A, 2, 8), note(D, 2, 8)];
+
+const GAME_OVER_TRACK: Track = Track {
+    tempo: 10,
+    looping: false,
+    voices: &[
+        Voice { flags: TONE_PULSE2 | TONE_MODE2, volume: 20, gate: 0, reserved: true, patterns: &[GAME_OVER_LEAD] },
+        Voice { flags: TONE_TRIANGLE, volume: 30, gate: 0, reserved: false, patterns: &[GAME_OVER_BASS] },
+    ],
+};
@@ -0,0 +1,19 @@
+use crate::wasm4::NETPLAY;
+
+const NETPLAY_ACTIVE: u8 = 0b100;
+const NETPLAY_PLAYER_INDEX: u8 = 0b011;
+
+/// Whether this cart is one of several in a netplay session. Every cart then receives the same
+/// gamepad inputs and has to simulate exactly the same game from them.
+pub fn active() -> bool {
+    unsafe { *NETPLAY & NETPLAY_ACTIVE != 0 }
+}
+
+/// The player whose gamepad is plugged into this cart, during a netplay session.
+pub fn local_player() -> Option<usize> {
+    if active() {
+        Some(unsafe { *NETPLAY & NETPLAY_PLAYER_INDEX } as usize)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,157 @@
+use crate::input::{Action, ACTION_COUNT, ALL_ACTIONS, button_name, Bindings, Input};
+use crate::menu::{MenuState, set_item_colours};
+use crate::settings::Settings;
+use crate::State;
+use crate::State::{Menu, Options};
+use crate::wasm4::{DRAW_COLORS, text};
+
+const OPTION_COUNT: u8 = 10;
+// one per action, then "Defaults" and "Back"
+const BUTTON_ITEM_COUNT: u8 = ACTION_COUNT as u8 + 2;
+
+#[derive(Copy, Clone, PartialEq)]
+enum Page {
+    Settings,
+    Buttons,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Rebinding {
+    Off,
+    // waiting for the button to bind to the selected action
+    Listening,
+    // the new button is still down, and letting go of it shouldn't count as a menu press
+    WaitingForRelease,
+}
+
+#[derive(Copy, Clone)]
+pub struct OptionsState {
+    selected: u8,
+    pressed: bool,
+    page: Page,
+    rebinding: Rebinding,
+    menu: MenuState,
+}
+
+impl OptionsState {
+    pub fn new(menu: MenuState) -> Self {
+        Self {
+            selected: 0,
+            pressed: false,
+            page: Page::Settings,
+            rebinding: Rebinding::Off,
+            menu,
+        }
+    }
+}
+
+fn next_volume(volume: u8) -> u8 {
+    if volume >= 100 { 0 } else { volume + 25 }
+}
+
+pub fn update_options(state: OptionsState, settings: &mut Settings, input: &Input) -> State {
+    let mut new_state = state;
+    match new_state.rebinding {
+        Rebinding::Off => {},
+        Rebinding::Listening => {
+            // any button at all, including ones bound to navigating this page
+            if let Some(button) = input.new_button() {
+                settings.bindings.bind(ALL_ACTIONS[new_state.selected as usize], button);
+                new_state.rebinding = Rebinding::WaitingForRelease;
+            }
+            return Options(new_state);
+        },
+        Rebinding::WaitingForRelease => {
+            if !input.any_held() {
+                new_state.rebinding = Rebinding::Off;
+            }
+            return Options(new_state);
+        },
+    }
+
+    let item_count = match new_state.page {
+        Page::Settings => OPTION_COUNT,
+        Page::Buttons => BUTTON_ITEM_COUNT,
+    };
+    if input.repeated(Action::Up) {
+        new_state.selected = (new_state.selected + item_count - 1) % item_count;
+    }
+    if input.repeated(Action::Down) {
+        new_state.selected = (new_state.selected + 1) % item_count;
+    }
+    if input.held(Action::Fire) {
+        new_state.pressed = true;
+    } else if input.released(Action::Fire) {
+        new_state.pressed = false;
+        match (new_state.page, new_state.selected) {
+            (Page::Settings, 0) => settings.master_volume = next_volume(settings.master_volume),
+            (Page::Settings, 1) => settings.mono = !settings.mono,
+            (Page::Settings, 2) => settings.particles = !settings.particles,
+            (Page::Settings, 3) => settings.nebula = !settings.nebula,
+            (Page::Settings, 4) => settings.screen_shake = !settings.screen_shake,
+            (Page::Settings, 5) => settings.handling = settings.handling.next(),
+            (Page::Settings, 6) => settings.firing = settings.firing.next(),
+            (Page::Settings, 7) => settings.controls = settings.controls.next(),
+            (Page::Settings, 8) => {
+                new_state.page = Page::Buttons;
+                new_state.selected = 0;
+            },
+            (Page::Settings, _) => return Menu(new_state.menu),
+            (Page::Buttons, item) if (item as usize) < ACTION_COUNT => new_state.rebinding = Rebinding::Listening,
+            (Page::Buttons, item) if item == BUTTON_ITEM_COUNT - 2 => settings.bindings = Bindings::DEFAULT,
+            (Page::Buttons, _) => {
+                new_state.page = Page::Settings;
+                new_state.selected = 8;
+            },
+        }
+    }
+    Options(new_state)
+}
+
+pub fn render_options(state: OptionsState, settings: &Settings) {
+    if state.page == Page::Buttons {
+        render_buttons(state, settings);
+        return;
+    }
+    unsafe { *DRAW_COLORS = 0x0003 }
+    text("OPTIONS", 10, 10);
+    set_item_colours(0, state.selected, state.pressed);
+    text(format!("Volume: {}%", settings.master_volume).as_str(), 10, 30);
+    set_item_colours(1, state.selected, state.pressed);
+    text(if settings.mono { "Sound: Mono" } else { "Sound: Stereo" }, 10, 40);
+    set_item_colours(2, state.selected, state.pressed);
+    text(if settings.particles { "Particles: On" } else { "Particles: Off" }, 10, 50);
+    set_item_colours(3, state.selected, state.pressed);
+    text(if settings.nebula { "Nebula: On" } else { "Nebula: Off" }, 10, 60);
+    set_item_colours(4, state.selected, state.pressed);
+    text(if settings.screen_shake { "Screen shake: On" } else { "Screen shake: Off" }, 10, 70);
+    set_item_colours(5, state.selected, state.pressed);
+    text(format!("Handling: {}", settings.handling.name()).as_str(), 10, 80);
+    set_item_colours(6, state.selected, state.pressed);
+    text(format!("Firing: {}", settings.firing.name()).as_str(), 10, 90);
+    set_item_colours(7, state.selected, state.pressed);
+    text(format!("Controls: {}", settings.controls.name()).as_str(), 10, 100);
+    set_item_colours(8, state.selected, state.pressed);
+    text("Buttons", 10, 110);
+    set_item_colours(9, state.selected, state.pressed);
+    text("Back", 10, 130);
+}
+
+fn render_buttons(state: OptionsState, settings: &Settings) {
+    unsafe { *DRAW_COLORS = 0x0003 }
+    text("BUTTONS", 10, 10);
+    for (item, action) in ALL_ACTIONS.iter().enumerate() {
+        set_item_colours(item as u8, state.selected, state.pressed);
+        let button = if state.rebinding == Rebinding::Listening && state.selected == item as u8 { "..." } else { button_name(settings.bindings.button(*action)) };
+        text(format!("{}: {}", action.name(), button).as_str(), 10, 30 + item as i32 * 10);
+    }
+    set_item_colours(BUTTON_ITEM_COUNT - 2, state.selected, state.pressed);
+    text("Defaults", 10, 100);
+    set_item_colours(BUTTON_ITEM_COUNT - 1, state.selected, state.pressed);
+    text("Back", 10, 110);
+
+    unsafe { *DRAW_COLORS = 0x0003 }
+    if state.rebinding == Rebinding::Listening {
+        text("Press a button", 10, 140);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::wasm4::{DRAW_COLORS, oval, rect};
+
+const PARTICLE_COUNT: usize = 64;
+
+// positions and velocities are in sixteenths of a pixel
+const SUBPIXELS: i16 = 16;
+
+// the eight compass directions, scaled to a speed of roughly one pixel per frame
+const DIRECTIONS: [(i8, i8); 8] = [(16, 0), (11, 11), (0, 16), (-11, 11), (-16, 0), (-11, -11), (0, -16), (11, -11)];
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum ParticleKind {
+    None,
+    Ember,
+    Spark,
+    Exhaust,
+    Sparkle,
+}
+
+#[derive(Copy, Clone)]
+struct Particle {
+    x: i16,
+    y: i16,
+    dx: i8,
+    dy: i8,
+    life: u8,
+    kind: ParticleKind,
+}
+
+const EMPTY_PARTICLE: Particle = Particle {
+    x: 0,
+    y: 0,
+    dx: 0,
+    dy: 0,
+    life: 0,
+    kind: ParticleKind::None,
+};
+
+/// A fixed-size pool of purely cosmetic particles. When the pool is full the oldest particle is
+/// replaced, so spawning never fails.
+#[derive(Copy, Clone)]
+pub struct Particles {
+    particles: [Particle; PARTICLE_COUNT],
+    next: u8,
+    seed: u32,
+}
+
+impl Particles {
+    pub const fn new() -> Self {
+        Self {
+            particles: [EMPTY_PARTICLE; PARTICLE_COUNT],
+            next: 0,
+            seed: 1,
+        }
+    }
+
+    fn random(&mut self) -> u32 {
+        self.seed = self.seed.wrapping_mul(1103515245).wrapping_add(12345);
+        self.seed >> 16
+    }
+
+    fn spawn(&mut self, x: u8, y: u8, dx: i8, dy: i8, life: u8, kind: ParticleKind) {
+        self.particles[self.next as usize] = Particle {
+            x: x as i16 * SUBPIXELS,
+            y: y as i16 * SUBPIXELS,
+            dx,
+            dy,
+            life,
+            kind,
+        };
+        self.next = (self.next + 1) % PARTICLE_COUNT as u8;
+    }
+
+    pub fn explosion(&mut self, x: u8, y: u8) {
+        for (dx, dy) in DIRECTIONS {
+            let speed = (self.random() % 3) as i8 + 1;
+            let life = (self.random() % 8) as u8 + 12;
+            self.spawn(x, y, dx / 2 * speed / 2, dy / 2 * speed / 2, life, ParticleKind::Ember);
+        }
+    }
+
+    pub fn sparks(&mut self, x: u8, y: u8) {
+        for _ in 0..3 {
+            let (dx, dy) = DIRECTIONS[(self.random() % 8) as usize];
+            self.spawn(x, y, dx * 2, dy * 2, 6, ParticleKind::Spark);
+        }
+    }
+
+    pub fn thrust(&mut self, x: u8, y: u8) {
+        let dx = (self.random() % 9) as i8 - 4;
+        self.spawn(x, y, dx, 16, 8, ParticleKind::Exhaust);
+    }
+
+    pub fn sparkle(&mut self, x: u8, y: u8) {
+        for (dx, dy) in DIRECTIONS.iter().step_by(2) {
+            self.spawn(x, y, dx / 2, dy / 2, 24, ParticleKind::Sparkle);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.particles = [EMPTY_PARTICLE; PARTICLE_COUNT];
+    }
+
+    pub fn update(&mut self) {
+        for particle in self.particles.iter_mut().filter(|particle| particle.kind != ParticleKind::None) {
+            particle.x += particle.dx as i16;
+            particle.y += particle.dy as i16;
+            particle.life = particle.life.saturating_sub(1);
+            if particle.life == 0 {
+                *particle = EMPTY_PARTICLE;
+            }
+        }
+    }
+
+    pub fn render(&self, (camera_x, camera_y): (i32, i32)) {
+        for particle in self.particles.iter() {
+            let x = (particle.x / SUBPIXELS) as i32 + camera_x;
+            let y = (particle.y / SUBPIXELS) as i32 + camera_y;
+            match particle.kind {
+                ParticleKind::None => {},
+                ParticleKind::Ember => {
+                    if particle.life > 10 {
+                        unsafe { *DRAW_COLORS = 0x0044 }
+                        oval(x - 1, y - 1, 3, 3);
+                    } else {
+                        unsafe { *DRAW_COLORS = if particle.life > 5 { 0x0003 } else { 0x0002 } }
+                        rect(x, y, 1, 1);
+                    }
+                },
+                ParticleKind::Spark => {
+                    unsafe { *DRAW_COLORS = 0x0004 }
+                    rect(x, y, 1, 1);
+                },
+                ParticleKind::Exhaust => {
+                    unsafe { *DRAW_COLORS = if particle.life > 4 { 0x0003 } else { 0x0002 } }
+                    rect(x, y, 1, 1);
+                },
+                ParticleKind::Sparkle => {
+                    // twinkle by only drawing every other pair of frames
+                    if particle.life / 2 % 2 == 0 {
+                        unsafe { *DRAW_COLORS = 0x0003 }
+                        rect(x, y, 1, 1);
+                    }
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use crate::input::{ACTION_COUNT, Bindings};
+use crate::settings::{Controls, Firing, Handling, Settings};
+use crate::upgrades::{UPGRADE_COUNT, Upgrades};
+use crate::wasm4::{diskr, diskw};
+
+// bump whenever the layout changes, and teach `from_bytes` to read the old one
+const SAVE_VERSION: u8 = 4;
+const COINS_START: usize = 9 + ACTION_COUNT;
+// a count of the upgrades, then each one's level, so new upgrades don't need a new version
+const UPGRADES_START: usize = COINS_START + 4;
+const SAVE_SIZE: usize = UPGRADES_START + 1 + UPGRADE_COUNT;
+
+/// Everything kept between sessions in the console's persistent storage.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Save {
+    pub settings: Settings,
+    // banked from every run so far
+    pub coins: u32,
+    pub upgrades: Upgrades,
+}
+
+impl Save {
+    pub const fn new() -> Self {
+        Self {
+            settings: Settings::new(),
+            coins: 0,
+            upgrades: Upgrades::NONE,
+        }
+    }
+
+    /// Reads the save from disk, falling back to defaults if there isn't one or it can't be read.
+    pub fn load() -> Self {
+        let mut bytes = [0u8; SAVE_SIZE];
+        let read = unsafe { diskr(bytes.as_mut_ptr(), SAVE_SIZE as u32) } as usize;
+        Save::from_bytes(&bytes[..read.min(SAVE_SIZE)])
+    }
+
+    pub fn write(&self) {
+        let bytes = self.to_bytes();
+        unsafe { diskw(bytes.as_ptr(), bytes.len() as u32) };
+    }
+
+    fn to_bytes(self) -> [u8; SAVE_SIZE] {
+        let settings = self.settings;
+        let mut bytes = [0u8; SAVE_SIZE];
+        bytes[0] = SAVE_VERSION;
+        bytes[1] = settings.master_volume;
+        bytes[2] = settings.mono as u8;
+        bytes[3] = settings.particles as u8;
+        bytes[4] = settings.nebula as u8;
+        bytes[5] = settings.screen_shake as u8;
+        bytes[6] = match settings.handling {
+            Handling::Tight => 0,
+            Handling::Normal => 1,
+            Handling::Loose => 2,
+        };
+        bytes[7] = match settings.controls {
+            Controls::Gamepad => 0,
+            Controls::Mouse => 1,
+        };
+        bytes[8] = match settings.firing {
+            Firing::Hold => 0,
+            Firing::Toggle => 1,
+            Firing::HoldToStop => 2,
+        };
+        bytes[9..COINS_START].copy_from_slice(&settings.bindings.to_bytes());
+        bytes[COINS_START..UPGRADES_START].copy_from_slice(&self.coins.to_le_bytes());
+        bytes[UPGRADES_START] = UPGRADE_COUNT as u8;
+        bytes[UPGRADES_START + 1..].copy_from_slice(&self.upgrades.to_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let defaults = Save::new();
+        let default_settings = defaults.settings;
+        let read_firing = || bytes.get(8).map(|firing| match firing {
+            1 => Firing::Toggle,
+            2 => Firing::HoldToStop,
+            _ => Firing::Hold,
+        });
+        let read_coins = || bytes.get(COINS_START..UPGRADES_START).and_then(|coins| coins.try_into().ok()).map(u32::from_le_bytes);
+        let read_upgrades = || bytes.get(UPGRADES_START).and_then(|count| {
+            let count = (*count as usize).min(UPGRADE_COUNT);
+            bytes.get(UPGRADES_START + 1..UPGRADES_START + 1 + count).map(Upgrades::from_bytes)
+        });
+        // version 1 saves came before the firing setting, 1 and 2 before coins, and 3 before upgrades
+        let (firing, bindings_start, coins, upgrades) = match bytes.first() {
+            Some(1) => (Some(default_settings.firing), 8, Some(0), Some(Upgrades::NONE)),
+            Some(2) => (read_firing(), 9, Some(0), Some(Upgrades::NONE)),
+            Some(3) => (read_firing(), 9, read_coins(), Some(Upgrades::NONE)),
+            Some(4) => (read_firing(), 9, read_coins(), read_upgrades()),
+            _ => (None, 0, None, None),
+        };
+        let (Some(firing), Some(bindings), Some(coins), Some(upgrades)) = (firing, bytes.get(bindings_start..bindings_start + ACTION_COUNT), coins, upgrades) else {
+            return defaults;
+        };
+        let settings = Settings {
+            master_volume: bytes[1].min(100),
+            mono: bytes[2] != 0,
+            particles: bytes[3] != 0,
+            nebula: bytes[4] != 0,
+            screen_shake: bytes[5] != 0,
+            handling: match bytes[6] {
+                0 => Handling::Tight,
+                1 => Handling::Normal,
+                2 => Handling::Loose,
+                _ => default_settings.handling,
+            },
+            firing,
+            controls: match bytes[7] {
+                1 => Controls::Mouse,
+                _ => Controls::Gamepad,
+            },
+            bindings: <[u8; ACTION_COUNT]>::try_from(bindings).ok()
+                .and_then(Bindings::from_bytes)
+                .unwrap_or(default_settings.bindings),
+        };
+        Save { settings, coins, upgrades }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Action;
+    use crate::upgrades::Upgrade;
+    use crate::wasm4::BUTTON_2;
+
+    #[test]
+    fn settings_coins_and_upgrades_survive_a_round_trip() {
+        let mut save = Save::new();
+        save.coins = 123456;
+        save.upgrades.buy(Upgrade::Bombs, &mut save.coins);
+        save.settings.master_volume = 25;
+        save.settings.nebula = false;
+        save.settings.handling = Handling::Loose;
+        save.settings.firing = Firing::HoldToStop;
+        save.settings.controls = Controls::Mouse;
+        save.settings.bindings.bind(Action::Fire, BUTTON_2);
+        assert_eq!(Save::from_bytes(&save.to_bytes()), save);
+    }
+
+    #[test]
+    fn unreadable_saves_fall_back_to_defaults() {
+        assert_eq!(Save::from_bytes(&[]), Save::new());
+        let mut bytes = Save::new().to_bytes();
+        bytes[0] = SAVE_VERSION + 1;
+        assert_eq!(Save::from_bytes(&bytes), Save::new());
+        // two actions on the same button
+        let mut bytes = Save::new().to_bytes();
+        bytes[9] = bytes[10];
+        assert_eq!(Save::from_bytes(&bytes).settings.bindings, Bindings::DEFAULT);
+    }
+
+    #[test]
+    fn version_1_saves_still_load() {
+        let mut save = Save::new();
+        save.settings.screen_shake = false;
+        save.settings.bindings.bind(Action::Fire, BUTTON_2);
+        let bytes = save.to_bytes();
+        let mut old_bytes = vec![1];
+        old_bytes.extend_from_slice(&bytes[1..8]);
+        old_bytes.extend_from_slice(&bytes[9..COINS_START]);
+        assert_eq!(Save::from_bytes(&old_bytes), save);
+    }
+
+    #[test]
+    fn version_2_saves_start_with_no_coins() {
+        let mut save = Save::new();
+        save.settings.firing = Firing::Toggle;
+        save.coins = 50;
+        let mut bytes = save.to_bytes();
+        bytes[0] = 2;
+        assert_eq!(Save::from_bytes(&bytes[..COINS_START]), Save { coins: 0, ..save });
+    }
+
+    #[test]
+    fn upgrades_added_since_a_save_start_unbought() {
+        let mut save = Save::new();
+        save.coins = 1000;
+        save.upgrades.buy(Upgrade::Health, &mut save.coins);
+        save.upgrades.buy(Upgrade::Magnet, &mut save.coins);
+        let mut bytes = save.to_bytes();
+        // as if only the first upgrade existed when it was written
+        bytes[UPGRADES_START] = 1;
+        let loaded = Save::from_bytes(&bytes[..UPGRADES_START + 2]);
+        assert_eq!(loaded.upgrades.level(Upgrade::Health), 1);
+        assert_eq!(loaded.upgrades.level(Upgrade::Magnet), 0);
+        assert_eq!(loaded.coins, save.coins);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::input::Bindings;
+
+/// How quickly the ship speeds up and slows down.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Handling {
+    Tight,
+    Normal,
+    Loose,
+}
+
+impl Handling {
+    pub fn next(self) -> Self {
+        match self {
+            Handling::Tight => Handling::Normal,
+            Handling::Normal => Handling::Loose,
+            Handling::Loose => Handling::Tight,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Handling::Tight => "Tight",
+            Handling::Normal => "Normal",
+            Handling::Loose => "Loose",
+        }
+    }
+}
+
+/// What player 1 steers their ship with.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Controls {
+    Gamepad,
+    // the ship chases the pointer, left button fires and right drops a bomb
+    Mouse,
+}
+
+impl Controls {
+    pub fn next(self) -> Self {
+        match self {
+            Controls::Gamepad => Controls::Mouse,
+            Controls::Mouse => Controls::Gamepad,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Controls::Gamepad => "Gamepad",
+            Controls::Mouse => "Mouse",
+        }
+    }
+}
+
+/// When the ship fires. The last two are for players who'd rather not hold a button down the
+/// whole game.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Firing {
+    Hold,
+    // a tap starts firing and another stops it
+    Toggle,
+    // fires all the time, except while the button is held
+    HoldToStop,
+}
+
+impl Firing {
+    pub fn next(self) -> Self {
+        match self {
+            Firing::Hold => Firing::Toggle,
+            Firing::Toggle => Firing::HoldToStop,
+            Firing::HoldToStop => Firing::Hold,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Firing::Hold => "Hold",
+            Firing::Toggle => "Toggle",
+            Firing::HoldToStop => "Hold stops",
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Settings {
+    pub master_volume: u8,
+    pub mono: bool,
+    pub particles: bool,
+    pub nebula: bool,
+    pub screen_shake: bool,
+    pub handling: Handling,
+    pub firing: Firing,
+    pub controls: Controls,
+    pub bindings: Bindings,
+}
+
+impl Settings {
+    pub const fn new() -> Self {
+        Self {
+            master_volume: 100,
+            mono: false,
+            particles: true,
+            nebula: true,
+            screen_shake: true,
+            handling: Handling::Normal,
+            firing: Firing::Hold,
+            controls: Controls::Gamepad,
+            bindings: Bindings::DEFAULT,
+        }
+    }
+
+    /// These settings with everything that changes how the game plays put back to the defaults, so
+    /// that every cart in a netplay session simulates the same game. The mouse isn't sent to the
+    /// other carts, so it's left out too, and everyone's buttons do the same thing.
+    pub fn shared(self) -> Self {
+        Self {
+            handling: Settings::new().handling,
+            firing: Settings::new().firing,
+            controls: Settings::new().controls,
+            bindings: Settings::new().bindings,
+            ..self
+        }
+    }
+}
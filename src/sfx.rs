@@ -0,0 +1,219 @@
+use crate::settings::Settings;
+use crate::wasm4::{SCREEN_SIZE, tone, TONE_NOISE, TONE_PAN_LEFT, TONE_PAN_RIGHT, TONE_PULSE1, TONE_TRIANGLE};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Sfx {
+    Shoot,
+    HitEnemy,
+    Hurt,
+    PowerUp,
+    EnemyShoot,
+    Bomb,
+    Coin,
+}
+
+const SFX_COUNT: usize = 7;
+
+// in order of priority, highest first
+const ALL_SFX: [Sfx; SFX_COUNT] = [Sfx::Bomb, Sfx::Hurt, Sfx::PowerUp, Sfx::HitEnemy, Sfx::Coin, Sfx::EnemyShoot, Sfx::Shoot];
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Pan {
+    Centre,
+    Left,
+    Right,
+}
+
+impl Pan {
+    fn from_x(x: u8) -> Self {
+        let third = SCREEN_SIZE / 3;
+        if (x as u32) < third {
+            Pan::Left
+        } else if (x as u32) >= SCREEN_SIZE - third {
+            Pan::Right
+        } else {
+            Pan::Centre
+        }
+    }
+
+    // the same sound requested from both sides at once is played in the centre
+    fn merge(self, other: Pan) -> Self {
+        if self == other { self } else { Pan::Centre }
+    }
+
+    fn flags(self) -> u32 {
+        match self {
+            Pan::Centre => 0,
+            Pan::Left => TONE_PAN_LEFT,
+            Pan::Right => TONE_PAN_RIGHT,
+        }
+    }
+}
+
+struct Sound {
+    frequency: u32,
+    duration: u32,
+    volume: u32,
+    channel: u32,
+    priority: u8,
+    // fewest frames allowed between two plays of the same sound
+    min_interval: u8,
+}
+
+impl Sound {
+    fn length(&self) -> u8 {
+        let attack = self.duration >> 24;
+        let decay = (self.duration >> 16) & 0xff;
+        let release = (self.duration >> 8) & 0xff;
+        let sustain = self.duration & 0xff;
+        (attack + decay + sustain + release).min(255) as u8
+    }
+}
+
+impl Sfx {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn sound(self) -> Sound {
+        match self {
+            Sfx::Shoot => Sound {
+                frequency: (220 << 16) | 270,
+                duration: 8 << 16,
+                volume: 50,
+                channel: TONE_NOISE,
+                priority: 1,
+                min_interval: 4,
+            },
+            Sfx::HitEnemy => Sound {
+                frequency: (70 << 16) | 90,
+                duration: (8 << 16) | (8 << 24),
+                volume: 100,
+                channel: TONE_TRIANGLE,
+                priority: 2,
+                min_interval: 6,
+            },
+            Sfx::Hurt => Sound {
+                frequency: (70 << 16) | 110,
+                duration: (8 << 16) | (8 << 24) | 10,
+                volume: 100,
+                channel: TONE_PULSE1,
+                priority: 4,
+                min_interval: 0,
+            },
+            Sfx::PowerUp => Sound {
+                frequency: (870 << 16) | 600,
+                duration: (8 << 16) | 12,
+                volume: 100,
+                channel: TONE_TRIANGLE,
+                priority: 3,
+                min_interval: 0,
+            },
+            Sfx::EnemyShoot => Sound {
+                frequency: (300 << 16) | 600,
+                duration: 6 << 16,
+                volume: 40,
+                channel: TONE_PULSE1,
+                priority: 1,
+                min_interval: 4,
+            },
+            Sfx::Bomb => Sound {
+                frequency: (40 << 16) | 400,
+                duration: (20 << 16) | (20 << 8) | 10,
+                volume: 100,
+                channel: TONE_NOISE,
+                priority: 5,
+                min_interval: 0,
+            },
+            Sfx::Coin => Sound {
+                frequency: (1400 << 16) | 1000,
+                duration: 6,
+                volume: 60,
+                channel: TONE_TRIANGLE,
+                priority: 2,
+                min_interval: 3,
+            },
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+struct ChannelState {
+    priority: u8,
+    frames_left: u8,
+}
+
+const IDLE_CHANNEL: ChannelState = ChannelState {
+    priority: 0,
+    frames_left: 0,
+};
+
+/// Collects the sound effects requested during a frame and plays them all at once at the end of it.
+#[derive(Copy, Clone)]
+pub struct SfxQueue {
+    requested: [Option<Pan>; SFX_COUNT],
+    cooldowns: [u8; SFX_COUNT],
+    channels: [ChannelState; 4],
+}
+
+impl SfxQueue {
+    pub const fn new() -> Self {
+        Self {
+            requested: [None; SFX_COUNT],
+            cooldowns: [0; SFX_COUNT],
+            channels: [IDLE_CHANNEL; 4],
+        }
+    }
+
+    pub fn request(&mut self, sfx: Sfx) {
+        self.request_panned(sfx, Pan::Centre);
+    }
+
+    /// Requests a sound panned towards the side of the screen that `x` is on.
+    pub fn request_at(&mut self, sfx: Sfx, x: u8) {
+        self.request_panned(sfx, Pan::from_x(x));
+    }
+
+    fn request_panned(&mut self, sfx: Sfx, pan: Pan) {
+        let requested = &mut self.requested[sfx.index()];
+        *requested = Some(requested.map_or(pan, |existing| existing.merge(pan)));
+    }
+
+    /// Bitmask of the channels a sound effect is still playing on.
+    pub fn busy_channels(&self) -> u8 {
+        self.channels.iter().enumerate()
+            .filter(|(_, channel)| channel.frames_left > 0)
+            .fold(0, |mask, (i, _)| mask | 1 << i)
+    }
+
+    /// Plays the requested sounds, at most one per channel, skipping any channel in `reserved_channels`.
+    /// A sound only interrupts another on the same channel if it has at least the same priority.
+    pub fn flush(&mut self, reserved_channels: u8, settings: &Settings) {
+        for channel in self.channels.iter_mut() {
+            channel.frames_left = channel.frames_left.saturating_sub(1);
+        }
+        for cooldown in self.cooldowns.iter_mut() {
+            *cooldown = cooldown.saturating_sub(1);
+        }
+
+        for sfx in ALL_SFX {
+            let Some(pan) = self.requested[sfx.index()] else { continue };
+            if self.cooldowns[sfx.index()] > 0 {
+                continue;
+            }
+            let sound = sfx.sound();
+            let channel = &mut self.channels[sound.channel as usize];
+            if reserved_channels & (1 << sound.channel) != 0 || channel.frames_left > 0 && channel.priority > sound.priority {
+                continue;
+            }
+            let pan = if settings.mono { Pan::Centre } else { pan };
+            tone(sound.frequency, sound.duration, sound.volume * settings.master_volume as u32 / 100, sound.channel | pan.flags());
+            *channel = ChannelState {
+                priority: sound.priority,
+                frames_left: sound.length(),
+            };
+            self.cooldowns[sfx.index()] = sound.min_interval;
+        }
+        self.requested = [None; SFX_COUNT];
+    }
+}
@@ -0,0 +1,73 @@
+use crate::input::{Action, Input};
+use crate::menu::{MenuState, set_item_colours};
+use crate::save::Save;
+use crate::State;
+use crate::State::{Menu, Shop};
+use crate::upgrades::{ALL_UPGRADES, UPGRADE_COUNT};
+use crate::wasm4::{DRAW_COLORS, text};
+
+// one per upgrade, then "Back"
+const ITEM_COUNT: u8 = UPGRADE_COUNT as u8 + 1;
+
+#[derive(Copy, Clone)]
+pub struct ShopState {
+    selected: u8,
+    pressed: bool,
+    menu: MenuState,
+}
+
+impl ShopState {
+    pub fn new(menu: MenuState) -> Self {
+        Self {
+            selected: 0,
+            pressed: false,
+            menu,
+        }
+    }
+}
+
+/// Spends the save's coins on whichever upgrade is picked, when there are enough of them.
+pub fn update_shop(state: ShopState, save: &mut Save, input: &Input) -> State {
+    let mut new_state = state;
+    if input.repeated(Action::Up) {
+        new_state.selected = (new_state.selected + ITEM_COUNT - 1) % ITEM_COUNT;
+    }
+    if input.repeated(Action::Down) {
+        new_state.selected = (new_state.selected + 1) % ITEM_COUNT;
+    }
+    if input.held(Action::Fire) {
+        new_state.pressed = true;
+    } else if input.released(Action::Fire) {
+        new_state.pressed = false;
+        match ALL_UPGRADES.get(new_state.selected as usize) {
+            Some(upgrade) => {
+                save.upgrades.buy(*upgrade, &mut save.coins);
+            },
+            None => return Menu(new_state.menu),
+        }
+    }
+    Shop(new_state)
+}
+
+pub fn render_shop(state: ShopState, save: &Save) {
+    unsafe { *DRAW_COLORS = 0x0003 }
+    text("SHOP", 10, 10);
+    let coins = format!("${}", save.coins);
+    text(coins.as_str(), 150 - 8 * coins.len() as i32, 10);
+    for (item, upgrade) in ALL_UPGRADES.iter().enumerate() {
+        set_item_colours(item as u8, state.selected, state.pressed);
+        let level = save.upgrades.level(*upgrade);
+        let cost = match upgrade.cost(level) {
+            Some(cost) => format!("${}", cost),
+            None => String::from("Max"),
+        };
+        text(format!("{:<7}{}/{} {}", upgrade.name(), level, upgrade.max_level(), cost).as_str(), 10, 30 + item as i32 * 10);
+    }
+    set_item_colours(ITEM_COUNT - 1, state.selected, state.pressed);
+    text("Back", 10, 80);
+
+    unsafe { *DRAW_COLORS = 0x0003 }
+    if let Some(upgrade) = ALL_UPGRADES.get(state.selected as usize) {
+        text(upgrade.description(), 2, 140);
+    }
+}
@@ -1,32 +1,168 @@
-use crate::wasm4::{blit, blit_sub};
+use crate::wasm4::{blit_sub, BLIT_FLIP_X, BLIT_FLIP_Y, BLIT_ROTATE};
+
+// every sprite, packed into a single sheet by build.rs
+#[allow(dead_code)]
+mod atlas {
+    use super::Frame;
+    include!(concat!(env!("OUT_DIR"), "/atlas.rs"));
+}
+
+use atlas::{aimer, bullet, eye, powerup, ship, ATLAS, ATLAS_FLAGS, ATLAS_WIDTH};
+
+/// A region of the atlas, and how it should be flipped when drawn.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Frame {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    flags: u32,
+}
+
+impl Frame {
+    const fn flip_x(self) -> Self {
+        Frame { flags: self.flags ^ BLIT_FLIP_X, ..self }
+    }
+
+    const fn flip_y(self) -> Self {
+        Frame { flags: self.flags ^ BLIT_FLIP_Y, ..self }
+    }
+
+    // a quarter turn anticlockwise, applied after any flips
+    const fn rotate(self) -> Self {
+        Frame { flags: self.flags | BLIT_ROTATE, ..self }
+    }
+}
+
+/// A sprite drawn pointing up, and pointing up and to the right, from which the other six
+/// compass directions are made by flipping and rotating.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Directional {
+    up: Frame,
+    up_right: Frame,
+}
+
+impl Directional {
+    /// The frame pointing closest to the direction of (`dx`, `dy`), or up if there is no direction.
+    pub fn pointing(&self, dx: i32, dy: i32) -> Frame {
+        // tan(22.5 degrees) is about 5/12, so these split the circle into eight even slices
+        if dx.abs() * 12 <= dy.abs() * 5 {
+            if dy > 0 { self.up.flip_y() } else { self.up }
+        } else if dy.abs() * 12 <= dx.abs() * 5 {
+            if dx < 0 { self.up.rotate() } else { self.up.flip_y().rotate() }
+        } else {
+            let frame = if dx < 0 { self.up_right.flip_x() } else { self.up_right };
+            if dy > 0 { frame.flip_y() } else { frame }
+        }
+    }
+}
+
+// aimer
+pub const AIMER: Directional = Directional { up: aimer(0), up_right: aimer(1) };
+
+// bullet
+pub const BULLET: Directional = Directional { up: bullet(0), up_right: bullet(1) };
 
 // eye
-const EYE_WIDTH: u32 = 8;
-const EYE_HEIGHT: u32 = 8;
-const EYE_FLAGS: u32 = 1; // BLIT_2BPP
-const EYE: [u8; 16] = [ 0x0f,0xf0,0x3a,0xac,0xe5,0x5b,0xd7,0xd7,0xd7,0xd7,0xe5,0x5b,0x3a,0xac,0x0f,0xf0 ];
+pub const EYE_OPEN: Frame = eye(0);
+pub const EYE_LOOK_LEFT: Frame = eye(1);
+pub const EYE_LOOK_RIGHT: Frame = EYE_LOOK_LEFT.flip_x();
+pub const EYE_LOOK_UP: Frame = eye(2);
+pub const EYE_LOOK_DOWN: Frame = EYE_LOOK_UP.flip_y();
+pub const EYE_HALF_CLOSED: Frame = eye(3);
+pub const EYE_CLOSED: Frame = eye(4);
 
 // ship
-const SHIP_WIDTH: u32 = 8;
-const SHIP_HEIGHT: u32 = 8;
-const SHIP_FLAGS: u32 = 1; // BLIT_2BPP
-const SHIP: [u8; 16] = [ 0x02,0x80,0x09,0x60,0x09,0x60,0x2d,0x78,0x2d,0x78,0xbd,0x7e,0x8d,0x72,0x03,0xc0 ];
+pub const SHIP: Frame = ship(0);
+pub const SHIP_BANK_LEFT_SLIGHT: Frame = ship(1);
+pub const SHIP_BANK_LEFT: Frame = ship(2);
+pub const SHIP_BANK_RIGHT_SLIGHT: Frame = SHIP_BANK_LEFT_SLIGHT.flip_x();
+pub const SHIP_BANK_RIGHT: Frame = SHIP_BANK_LEFT.flip_x();
 
 // powerup
-const POWERUP_WIDTH: u32 = 8;
-const POWERUP_HEIGHT: u32 = 8;
-const POWERUP_FLAGS: u32 = 1; // BLIT_2BPP
-const POWERUP: [u8; 16] = [ 0x0f,0xf0,0x3b,0xec,0xe7,0xdb,0xff,0xff,0xff,0xff,0xe7,0xdb,0x3b,0xec,0x0f,0xf0 ];
+pub const POWERUP: Frame = powerup(0);
+pub const POWERUP_BRIGHT: Frame = powerup(1);
+pub const POWERUP_FLASH: Frame = powerup(2);
 
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum LoopMode {
+    /// Stops on the last frame.
+    Once,
+    Loop,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Animation {
+    frames: &'static [Frame],
+    frame_duration: u8,
+    mode: LoopMode,
+}
+
+impl Animation {
+    /// Length of one play-through, in frames.
+    pub const fn length(&self) -> u32 {
+        self.frames.len() as u32 * self.frame_duration as u32
+    }
+
+    pub fn frame(&self, time: u32) -> Frame {
+        let step = time / self.frame_duration as u32;
+        let count = self.frames.len() as u32;
+        let index = match self.mode {
+            LoopMode::Once => step.min(count - 1),
+            LoopMode::Loop => step % count,
+        };
+        self.frames[index as usize]
+    }
+}
+
+pub const EYE_BLINK: Animation = Animation {
+    frames: &[EYE_HALF_CLOSED, EYE_CLOSED, EYE_CLOSED, EYE_HALF_CLOSED],
+    frame_duration: 3,
+    mode: LoopMode::Once,
+};
+
+pub const POWERUP_PULSE: Animation = Animation {
+    frames: &[POWERUP, POWERUP_BRIGHT, POWERUP_FLASH, POWERUP_BRIGHT],
+    frame_duration: 8,
+    mode: LoopMode::Loop,
+};
+
+/// The eye frame looking in the direction of (`dx`, `dy`), or straight ahead if that's close by.
+pub fn eye_looking(dx: i32, dy: i32) -> Frame {
+    if dx.abs() < 12 && dy.abs() < 12 {
+        EYE_OPEN
+    } else if dx.abs() > dy.abs() {
+        if dx < 0 { EYE_LOOK_LEFT } else { EYE_LOOK_RIGHT }
+    } else if dy < 0 {
+        EYE_LOOK_UP
+    } else {
+        EYE_LOOK_DOWN
+    }
+}
+
+/// The ship frame banking into a horizontal velocity of `dx`.
+pub fn ship_banking(dx: i8) -> Frame {
+    match dx {
+        i8::MIN..=-2 => SHIP_BANK_LEFT,
+        -1 => SHIP_BANK_LEFT_SLIGHT,
+        0 => SHIP,
+        1 => SHIP_BANK_RIGHT_SLIGHT,
+        2..=i8::MAX => SHIP_BANK_RIGHT,
+    }
+}
+
+pub fn draw(frame: Frame, x: i32, y: i32) {
+    blit_sub(&ATLAS, x, y, frame.width, frame.height, frame.x, frame.y, ATLAS_WIDTH, ATLAS_FLAGS | frame.flags)
+}
 
 pub fn render_eye(x: i32, y: i32) {
-    blit(&EYE, x, y, EYE_WIDTH, EYE_HEIGHT, EYE_FLAGS)
+    draw(EYE_OPEN, x, y)
 }
 
 pub fn render_ship(x: i32, y: i32) {
-    blit(&SHIP, x, y, SHIP_WIDTH, SHIP_HEIGHT, SHIP_FLAGS)
+    draw(SHIP, x, y)
 }
 
 pub fn render_powerup(x: i32, y: i32) {
-    blit(&POWERUP, x, y, POWERUP_WIDTH, POWERUP_HEIGHT, POWERUP_FLAGS)
-}
\ No newline at end of file
+    draw(POWERUP, x, y)
+}
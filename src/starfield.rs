@@ -0,0 +1,122 @@
+use crate::wasm4::{DRAW_COLORS, hline, rect, vline, SCREEN_SIZE};
+
+const LAYER_COUNT: usize = 3;
+const STAR_COUNT: usize = 30;
+
+// positions are in sixteenths of a pixel
+const SUBPIXELS: i32 = 16;
+const FIELD_SIZE: i32 = SCREEN_SIZE as i32 * SUBPIXELS;
+
+// how far each layer scrolls per frame at normal speed, furthest layer first
+const LAYER_SPEEDS: [i32; LAYER_COUNT] = [4, 8, 16];
+
+// speed multipliers, in sixteenths
+const NORMAL_SPEED: i32 = 16;
+const BOSS_SPEED: i32 = 32;
+const WARP_SPEED: i32 = 64;
+
+// how long the field rushes past when a boss arrives or is defeated
+const WARP_FRAMES: u8 = 90;
+
+// nebula bands repeat every this many pixels, so they drift in and out of view
+const NEBULA_PERIOD: i32 = 240;
+const NEBULA_WIDTHS: [u32; 12] = [20, 36, 52, 64, 70, 72, 70, 64, 52, 36, 20, 8];
+const NEBULA_CENTRES: [i32; 2] = [50, 110];
+
+#[derive(Copy, Clone)]
+struct Star {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Copy, Clone)]
+pub struct Starfield {
+    stars: [Star; STAR_COUNT],
+    nebula_y: i32,
+    speed: i32,
+    warp_frames: u8,
+    boss_active: bool,
+    seed: u32,
+}
+
+const fn next_seed(seed: u32) -> u32 {
+    seed.wrapping_mul(1103515245).wrapping_add(12345)
+}
+
+impl Starfield {
+    pub const fn new() -> Self {
+        let mut stars = [Star { x: 0, y: 0 }; STAR_COUNT];
+        let mut seed = 7;
+        let mut i = 0;
+        while i < STAR_COUNT {
+            seed = next_seed(seed);
+            let x = (seed >> 16) as i32 % FIELD_SIZE;
+            seed = next_seed(seed);
+            let y = (seed >> 16) as i32 % FIELD_SIZE;
+            stars[i] = Star { x, y };
+            i += 1;
+        }
+        Self {
+            stars,
+            nebula_y: 0,
+            speed: NORMAL_SPEED,
+            warp_frames: 0,
+            boss_active: false,
+            seed,
+        }
+    }
+
+    /// Scrolls the field. Layers shift against the player's horizontal movement, nearer layers
+    /// more so, and the whole field speeds up while a boss is on screen.
+    pub fn update(&mut self, player_dx: i8, boss_active: bool) {
+        if boss_active != self.boss_active {
+            self.boss_active = boss_active;
+            self.warp_frames = WARP_FRAMES;
+        }
+        self.warp_frames = self.warp_frames.saturating_sub(1);
+        let target_speed = if self.warp_frames > 0 {
+            WARP_SPEED
+        } else if boss_active {
+            BOSS_SPEED
+        } else {
+            NORMAL_SPEED
+        };
+        self.speed += (target_speed - self.speed).signum();
+
+        for (i, star) in self.stars.iter_mut().enumerate() {
+            let layer_speed = LAYER_SPEEDS[i % LAYER_COUNT];
+            star.x = (star.x - player_dx as i32 * layer_speed / 4).rem_euclid(FIELD_SIZE);
+            star.y += layer_speed * self.speed / NORMAL_SPEED;
+            if star.y >= FIELD_SIZE {
+                self.seed = next_seed(self.seed);
+                star.y -= FIELD_SIZE;
+                star.x = (self.seed >> 16) as i32 % FIELD_SIZE;
+            }
+        }
+        self.nebula_y = (self.nebula_y + 2 * self.speed / NORMAL_SPEED).rem_euclid(NEBULA_PERIOD * SUBPIXELS);
+    }
+
+    // everything is drawn in the same colour so the background never competes with the sprites
+    pub fn render(&self, nebula: bool) {
+        unsafe { *DRAW_COLORS = 0x0002 }
+        if nebula {
+            for (band, centre) in NEBULA_CENTRES.iter().enumerate() {
+                let top = (self.nebula_y / SUBPIXELS + band as i32 * NEBULA_PERIOD / 2) % NEBULA_PERIOD - 40;
+                // only every other line is drawn, for a dithered look
+                for (line, width) in NEBULA_WIDTHS.iter().enumerate().step_by(2) {
+                    hline(centre - *width as i32 / 2, top + line as i32, *width);
+                }
+            }
+        }
+        for (i, star) in self.stars.iter().enumerate() {
+            let x = star.x / SUBPIXELS;
+            let y = star.y / SUBPIXELS;
+            if i % LAYER_COUNT == LAYER_COUNT - 1 {
+                // the nearest stars streak as the field speeds up
+                vline(x, y, (self.speed / NORMAL_SPEED) as u32);
+            } else {
+                rect(x, y, 1, 1);
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+/// A permanent boost bought in the shop with coins, which every run after starts with.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Upgrade {
+    Health,
+    Weapon,
+    Bombs,
+    // a small magnet that's always on, which also adds to the range of the magnet buff
+    Magnet,
+}
+
+// new upgrades go on the end, so saves from before they existed still line up
+pub const UPGRADE_COUNT: usize = 4;
+
+pub const ALL_UPGRADES: [Upgrade; UPGRADE_COUNT] = [Upgrade::Health, Upgrade::Weapon, Upgrade::Bombs, Upgrade::Magnet];
+
+impl Upgrade {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Upgrade::Health => "Health",
+            Upgrade::Weapon => "Weapon",
+            Upgrade::Bombs => "Bombs",
+            Upgrade::Magnet => "Magnet",
+        }
+    }
+
+    // what the next level does, for the shop
+    pub fn description(self) -> &'static str {
+        match self {
+            Upgrade::Health => "+1 starting health",
+            Upgrade::Weapon => "Start with top gun",
+            Upgrade::Bombs => "+1 starting bomb",
+            Upgrade::Magnet => "Pull in pickups",
+        }
+    }
+
+    pub fn max_level(self) -> u8 {
+        match self {
+            Upgrade::Weapon => 1,
+            _ => 3,
+        }
+    }
+
+    /// How many coins the level after `level` costs, or none once the upgrade is maxed out.
+    pub fn cost(self, level: u8) -> Option<u32> {
+        if level >= self.max_level() {
+            return None;
+        }
+        let base = match self {
+            Upgrade::Health => 50,
+            Upgrade::Weapon => 150,
+            Upgrade::Bombs => 40,
+            Upgrade::Magnet => 30,
+        };
+        Some(base * (level as u32 + 1))
+    }
+}
+
+/// The level bought of each upgrade, from 0 for none.
+#[derive(Copy, Clone, PartialEq, Hash, Debug)]
+pub struct Upgrades {
+    levels: [u8; UPGRADE_COUNT],
+}
+
+impl Upgrades {
+    pub const NONE: Upgrades = Upgrades { levels: [0; UPGRADE_COUNT] };
+
+    pub fn level(&self, upgrade: Upgrade) -> u8 {
+        self.levels[upgrade.index()]
+    }
+
+    /// Spends coins on the next level of an upgrade, if there's one and it can be afforded.
+    pub fn buy(&mut self, upgrade: Upgrade, coins: &mut u32) -> bool {
+        match upgrade.cost(self.level(upgrade)) {
+            Some(cost) if cost <= *coins => {
+                *coins -= cost;
+                self.levels[upgrade.index()] += 1;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    pub fn to_bytes(self) -> [u8; UPGRADE_COUNT] {
+        self.levels
+    }
+
+    /// Upgrades read back from `to_bytes`. Any missing from the end, from before they were added,
+    /// haven't been bought.
+    pub fn from_bytes(levels: &[u8]) -> Self {
+        let mut upgrades = Upgrades::NONE;
+        for (upgrade, level) in ALL_UPGRADES.iter().zip(levels) {
+            upgrades.levels[upgrade.index()] = (*level).min(upgrade.max_level());
+        }
+        upgrades
+    }
+}